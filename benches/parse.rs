@@ -0,0 +1,80 @@
+//! Measures the parsing of large inputs, and the interning of their values.
+//! 
+//! Run via `cargo bench`; every case is run repeatedly, printing the average time per run.
+//! 
+//! Interning compares a linear scan over the values seen so far (as `Block::emplace` used to do)
+//! against the hashed table of `Block::dedup`, which hashes decimals by their bits.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use impral::lexer::Literal;
+use impral::parser::{Block, Expression};
+use rustc_hash::FxHashMap;
+
+/// Runs the function repeatedly, printing the average time per run.
+fn bench<T>(name: &str, runs: u32, mut f: impl FnMut() -> T) {
+    // Warm up caches and allocator.
+    black_box(f());
+    
+    let start = Instant::now();
+    for _ in 0..runs {
+        black_box(f());
+    }
+    println!("{name:<44} {:>12.3?} per run", start.elapsed() / runs);
+}
+
+/// Interns the values via a linear scan, returning the index of every value.
+fn intern_linear(values: &[Literal]) -> Vec<usize> {
+    let mut interned: Vec<&Literal> = Vec::new();
+    values.iter().map(|value| match interned.iter().position(|v| *v == value) {
+        Some(index) => index,
+        None => {
+            interned.push(value);
+            interned.len() - 1
+        },
+    }).collect()
+}
+
+/// Interns the values via a hashed table, returning the index of every value.
+fn intern_hashed(values: &[Literal]) -> Vec<usize> {
+    let mut interned: FxHashMap<&Literal, usize> = FxHashMap::default();
+    values.iter().map(|value| {
+        let next = interned.len();
+        *interned.entry(value).or_insert(next)
+    }).collect()
+}
+
+fn main() {
+    let list = |item: &dyn Fn(usize) -> String| {
+        let items: Vec<String> = (0..10_000).map(item).collect();
+        format!("echo [{}]", items.join(" "))
+    };
+    
+    let cases = [
+        ("10k distinct integers", list(&|i| i.to_string())),
+        ("10k distinct decimals", list(&|i| format!("{i}.5"))),
+        ("10k repeated integers", list(&|i| (i % 16).to_string())),
+        ("10k distinct strings", list(&|i| format!("item-{i}"))),
+    ];
+    
+    for (name, input) in &cases {
+        let block = impral::parse(input).expect("input should parse");
+        let values: Vec<Literal> = block.iter()
+            .filter_map(|(_, expr)| match expr {
+                Expression::Value(value) => Some(value.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(intern_linear(&values), intern_hashed(&values), "both interners should agree");
+        
+        bench(&format!("{name}: parse"), 10, || impral::parse(black_box(input)).expect("input should parse"));
+        bench(&format!("{name}: intern, linear scan"), 10, || intern_linear(black_box(&values)));
+        bench(&format!("{name}: intern, hashed table"), 10, || intern_hashed(black_box(&values)));
+        bench(&format!("{name}: Block::dedup"), 10, || {
+            let mut block: Block = block.clone();
+            block.dedup();
+            block
+        });
+    }
+}
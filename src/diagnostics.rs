@@ -0,0 +1,239 @@
+//! Rendering of errors as human-readable diagnostics, with source snippets.
+
+use std::fmt::Write;
+use yansi::{Color, Style};
+
+use crate::lexer::{LexError, LexErrorKind, SourceMap, Span};
+use crate::parser::ParseError;
+use crate::registry::ValidationError;
+
+#[cfg(test)]
+mod tests;
+
+/// An error message, pointing at the span of source that caused it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The message describing what went wrong.
+    pub message: String,
+    
+    /// The span of the offending source, if known.
+    pub span: Option<Span>,
+    
+    /// A short label, written next to the underlined span.
+    pub label: Option<String>,
+    
+    /// A hint on how to fix the problem.
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic with the given message.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: None,
+            label: None,
+            hint: None,
+        }
+    }
+    
+    /// Sets the span of the offending source.
+    #[must_use]
+    pub fn with_span(mut self, span: impl Into<Span>) -> Self {
+        self.span = Some(span.into());
+        self
+    }
+    
+    /// Sets the label written next to the underlined span.
+    #[must_use]
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+    
+    /// Sets the hint on how to fix the problem.
+    #[must_use]
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+    
+    /// Renders the diagnostic as plain text.
+    pub fn render(&self, map: &SourceMap) -> String {
+        self.render_styled(map, false)
+    }
+    
+    /// Renders the diagnostic as text with ANSI colours.
+    /// 
+    /// Colours are left out if they were disabled globally, via `yansi::Paint::disable`.
+    pub fn render_ansi(&self, map: &SourceMap) -> String {
+        self.render_styled(map, true)
+    }
+    
+    fn render_styled(&self, map: &SourceMap, color: bool) -> String {
+        let paint = |text: &str, style: Style| if color {
+            style.paint(text).to_string()
+        } else {
+            text.to_string()
+        };
+        
+        let error = Style::new(Color::Red).bold();
+        let gutter = Style::new(Color::Blue).bold();
+        let hint = Style::new(Color::Cyan).bold();
+        
+        let mut out = String::new();
+        let _ = writeln!(out, "{}: {}", paint("error", error), paint(&self.message, Style::default().bold()));
+        
+        if let Some(span) = self.span {
+            let start = map.locate(span.start);
+            let end = map.locate(span.end);
+            let line = map.line(start.line).unwrap_or_default();
+            
+            // Spans reaching past their first line are underlined up to its end.
+            let line_len = line.chars().count();
+            let end_col = if end.line == start.line {end.col} else {line_len + 1};
+            let width = end_col.saturating_sub(start.col).max(1);
+            
+            let number = start.line.to_string();
+            let pad = " ".repeat(number.len());
+            
+            let _ = writeln!(out, "{pad}{} {}", paint("-->", gutter), map.location(span.start));
+            let _ = writeln!(out, "{pad} {}", paint("|", gutter));
+            let _ = writeln!(out, "{} {line}", paint(&format!("{number} |"), gutter));
+            
+            let mut marker = format!("{}{}", " ".repeat(start.col - 1), paint(&"^".repeat(width), error));
+            if let Some(label) = &self.label {
+                let _ = write!(marker, " {}", paint(label, error));
+            }
+            let _ = writeln!(out, "{pad} {} {marker}", paint("|", gutter));
+            
+            if let Some(text) = &self.hint {
+                let _ = writeln!(out, "{pad} {} {}: {text}", paint("=", gutter), paint("hint", hint));
+            }
+        } else if let Some(text) = &self.hint {
+            let _ = writeln!(out, "{} {}: {text}", paint("=", gutter), paint("hint", hint));
+        }
+        
+        out
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        let diagnostic = Diagnostic::new(err.kind.to_string()).with_span(err.span);
+        match &err.kind {
+            LexErrorKind::InvalidObjectReference => diagnostic
+                .with_label("invalid reference")
+                .with_hint("object references are written as `@name`, `@\"name\"` or `@<uuid>`"),
+            LexErrorKind::IntegerOverflow(_) => diagnostic
+                .with_label("too large")
+                .with_hint(format!("integers must be between {} and {}", i64::MIN, i64::MAX)),
+            LexErrorKind::InvalidDecimal(_) => diagnostic
+                .with_label("invalid number"),
+            LexErrorKind::InvalidExponent(_) => diagnostic
+                .with_label("invalid exponent")
+                .with_hint("exponents are written as `e` followed by an integer, like `1e3`"),
+            LexErrorKind::UnterminatedString => diagnostic
+                .with_label("string starts here")
+                .with_hint("add the missing closing quote"),
+            LexErrorKind::UnterminatedArray => diagnostic
+                .with_label("array starts here")
+                .with_hint("add the missing `]`"),
+            LexErrorKind::UnclosedDelimiter(symbol) => diagnostic
+                .with_label("never closed")
+                .with_hint(format!("add the missing `{}`", symbol.get_delimiter().unwrap_or(*symbol))),
+            LexErrorKind::UnexpectedDelimiter(_) => diagnostic
+                .with_label("closes nothing")
+                .with_hint("remove it, or add the missing opening delimiter"),
+            LexErrorKind::MismatchedDelimiter(symbol, _) => diagnostic
+                .with_label(format!("expected `{}`", symbol.get_delimiter().unwrap_or(*symbol))),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let diagnostic = Diagnostic::new(err.to_string());
+        let diagnostic = match err.span() {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        };
+        
+        match err {
+            ParseError::Empty => diagnostic
+                .with_hint("enter a command"),
+            ParseError::Unrecognized(_, _) => Diagnostic {
+                message: "Unrecognized token".into(),
+                ..diagnostic
+            }.with_label("not understood"),
+            ParseError::ExpectButEnd(_, expected) => diagnostic
+                .with_label(format!("expected {expected}")),
+            ParseError::Unexpected(_, _) => diagnostic
+                .with_label("unexpected"),
+            ParseError::LexerError(_) => Diagnostic {
+                message: "Lexer failure".into(),
+                ..diagnostic
+            }.with_label("not understood"),
+            ParseError::ExpectButGot(_, expected, _) => diagnostic
+                .with_label(format!("expected {expected}")),
+            ParseError::Lex(err) => err.into(),
+            ParseError::PosArgAfterNomArg(_) => Diagnostic {
+                message: "Positional args cannot be written after nominal args".into(),
+                ..diagnostic
+            }.with_label("positional argument")
+                .with_hint("move the positional arguments in front of the named arguments and flags"),
+            ParseError::Incomplete { expected, .. } => diagnostic
+                .with_label(format!("expected {expected}")),
+        }
+    }
+}
+
+impl From<&ValidationError> for Diagnostic {
+    fn from(err: &ValidationError) -> Self {
+        let diagnostic = Diagnostic::new(err.to_string()).with_span(err.span());
+        match err {
+            ValidationError::UnknownCommand(_, _, suggestion) => {
+                let diagnostic = diagnostic.with_label("unknown command");
+                match suggestion {
+                    Some(name) => diagnostic.with_hint(format!("did you mean `{name}`?")),
+                    None => diagnostic,
+                }
+            },
+            ValidationError::Missing(_, _, param) => diagnostic
+                .with_label(format!("missing '{param}'")),
+            ValidationError::Extra(_, _, _) => diagnostic
+                .with_label("too many arguments"),
+            ValidationError::Unknown(_, _, _) => diagnostic
+                .with_label("unknown parameter"),
+            ValidationError::Duplicate(_, _, _) => diagnostic
+                .with_label("given more than once")
+                .with_hint("remove all but one of them"),
+            ValidationError::NotAFlag(_, _, param) => diagnostic
+                .with_label("not a flag")
+                .with_hint(format!("write it as `{param}=…` instead")),
+            ValidationError::Mismatch(_, _, _, expected, _) => diagnostic
+                .with_label(format!("expected a {expected}")),
+            ValidationError::StrayResult(_) => diagnostic
+                .with_label("no result")
+                .with_hint("`$` refers to the result passed on by pipes, `&&`, arrows and previous statements"),
+        }
+    }
+}
+
+impl From<ValidationError> for Diagnostic {
+    fn from(err: ValidationError) -> Self {
+        (&err).into()
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(err: LexError) -> Self {
+        (&err).into()
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(err: ParseError) -> Self {
+        (&err).into()
+    }
+}
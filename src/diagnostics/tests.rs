@@ -0,0 +1,110 @@
+//! Tests to ensure diagnostics are rendered correctly.
+
+use super::*;
+
+fn render(input: &str) -> String {
+    let err = crate::parse(input).expect_err("input should not parse");
+    Diagnostic::from(&err).render(&SourceMap::new(input))
+}
+
+#[test]
+fn render_parse_errors() {
+    assert_eq!(render("heal + 5"), "\
+error: Expected a flag, but got something else
+ --> 1:8
+  |
+1 | heal + 5
+  |        ^ expected a flag
+");
+    
+    assert_eq!(render("foo x=1 2"), "\
+error: Positional args cannot be written after nominal args
+ --> 1:9
+  |
+1 | foo x=1 2
+  |         ^ positional argument
+  = hint: move the positional arguments in front of the named arguments and flags
+");
+    
+    assert_eq!(render("x $a."), "\
+error: Expected member name for field access, but got something else
+ --> 1:6
+  |
+1 | x $a.
+  |      ^ expected member name for field access
+");
+    
+    assert_eq!(render(""), "\
+error: The stream of tokens is empty
+= hint: enter a command
+");
+}
+
+#[test]
+fn render_lex_errors() {
+    assert_eq!(render("foo 'bar"), "\
+error: Incomplete input, expected a closing quote
+ --> 1:5
+  |
+1 | foo 'bar
+  |     ^^^^ expected a closing quote
+");
+    
+    assert_eq!(render("a b\nfoo @"), "\
+error: Invalid object reference
+ --> 2:5
+  |
+2 | foo @
+  |     ^ invalid reference
+  = hint: object references are written as `@name`, `@\"name\"` or `@<uuid>`
+");
+    
+    let input = "foo 99999999999999999999";
+    let err = crate::lexer::tokenize(input).find_map(|t| match t.content {
+        crate::lexer::TokenContent::Error(err) => Some(err),
+        _ => None
+    }).expect("input should fail to lex");
+    
+    let map = SourceMap::new(input).with_name("console");
+    let plain = Diagnostic::from(&err).render(&map);
+    assert!(plain.starts_with("error: Integer '99999999999999999999' does not fit into 64 bits\n --> console:1:5\n"));
+    assert!(plain.contains("\n  |     ^^^^^^^^^^^^^^^^^^^^ too large\n"));
+}
+
+#[test]
+fn render_ansi() {
+    let input = "heal + 5";
+    let err = crate::parse(input).expect_err("input should not parse");
+    let diagnostic = Diagnostic::from(&err);
+    let map = SourceMap::new(input);
+    
+    let ansi = diagnostic.render_ansi(&map);
+    assert!(ansi.contains("\u{1b}["));
+    
+    let stripped = ansi.split('\u{1b}')
+        .enumerate()
+        .map(|(i, s)| if i == 0 {s} else {&s[s.find('m').map(|m| m + 1).unwrap_or(0)..]})
+        .collect::<String>();
+    assert_eq!(stripped, diagnostic.render(&map));
+}
+
+#[test]
+fn render_validation_errors() {
+    use crate::registry::{CommandRegistry, Signature, BoundArgs, validate};
+    use crate::eval::{Context, Value};
+    
+    let mut registry = CommandRegistry::new();
+    registry.register(Signature::new("heal"), |_: &mut Context, _: &BoundArgs| Ok(Value::Nil));
+    
+    let input = "hael";
+    let block = crate::parse(input).expect("input should parse");
+    let errors = validate(&block, &registry);
+    assert_eq!(Diagnostic::from(&errors[0]).render(&SourceMap::new(input)), "\
+error: Unknown command 'hael'
+ --> 1:1
+  |
+1 | hael
+  | ^^^^ unknown command
+  = hint: did you mean `heal`?
+");
+}
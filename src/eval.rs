@@ -0,0 +1,251 @@
+//! Evaluation of parsed [`Block`]s by walking the linearized expression tree.
+
+use std::borrow::Cow;
+
+use rustc_hash::FxHashMap;
+use smartstring::alias::CompactString;
+
+use crate::lexer::Literal;
+use crate::parser::{Block, BlockRef, Expression, FnCall};
+
+pub use crate::values::*;
+
+pub mod context;
+pub use context::*;
+
+pub mod host;
+pub use host::*;
+
+pub mod error;
+pub use error::*;
+
+pub mod pipe;
+pub use pipe::*;
+
+pub mod arrow;
+pub use arrow::*;
+
+pub mod closure;
+pub use closure::*;
+
+/// Evaluates the given block, starting at its entrypoint.
+pub fn eval(
+    block: &Block,
+    host: &mut dyn CommandHost,
+    ctx: &mut Context
+) -> Result<Value, EvalError> {
+    Interpreter::new(block, host).run(ctx)
+}
+
+/// A tree-walking interpreter for a single [`Block`].
+pub struct Interpreter<'b, 'h> {
+    /// The block being evaluated.
+    pub(crate) block: &'b Block,
+    /// The host that commands are dispatched to.
+    pub(crate) host: &'h mut dyn CommandHost,
+    /// The expressions deferred via `~>`, together with the value to bind to `$`.
+    pub(crate) deferred: Vec<(BlockRef, Value)>,
+}
+
+impl<'b, 'h> Interpreter<'b, 'h> {
+    /// Creates a new interpreter for the given block.
+    pub fn new(block: &'b Block, host: &'h mut dyn CommandHost) -> Self {
+        Self { block, host, deferred: Vec::new() }
+    }
+    
+    /// Evaluates the block, starting at its entrypoint.
+    /// 
+    /// Expressions deferred via `~>` are evaluated afterwards; their values are discarded.
+    pub fn run(&mut self, ctx: &mut Context) -> Result<Value, EvalError> {
+        let entry = self.block.entry().ok_or(EvalError::NoEntry)?;
+        let value = self.eval(ctx, entry)?;
+        self.run_deferred(ctx)?;
+        Ok(value)
+    }
+    
+    /// Evaluates the expression behind the given [`BlockRef`].
+    pub fn eval(&mut self, ctx: &mut Context, br: BlockRef) -> Result<Value, EvalError> {
+        match self.block.get(br) {
+            Expression::Empty => Ok(Value::Nil),
+            Expression::Value(literal) => match literal {
+                Literal::RefRes => Ok(ctx.result.clone()),
+                Literal::RefCtx => Ok(ctx.context.clone()),
+                Literal::RefVar(name) => ctx.locals.get(name)
+                    .cloned()
+                    .ok_or_else(|| EvalError::UndefinedVariable(name.clone())),
+                literal => Ok(Value::from(literal)),
+            },
+            Expression::FnCall(call) => self.eval_call(ctx, call, None),
+            Expression::Range(start, end, inclusive) => {
+                let start = self.eval(ctx, *start)?;
+                let end = self.eval(ctx, *end)?;
+                Ok(Value::Range(Box::new(RangeValue { start, end, inclusive: *inclusive })))
+            },
+            Expression::Field(target, name) => {
+                let target = self.eval(ctx, *target)?;
+                self.eval_field(ctx, target, name)
+            },
+            Expression::Index(target, index) => {
+                let target = self.eval(ctx, *target)?;
+                let index = self.eval(ctx, *index)?;
+                self.eval_index(ctx, target, index)
+            },
+            Expression::Method(target, call) => {
+                let target = self.eval(ctx, *target)?;
+                match self.block.get(*call) {
+                    Expression::FnCall(call) => self.eval_call(ctx, call, Some(target)),
+                    expr => Err(EvalError::ExpectButGot("a method call".into(), format!("{expr:?}").into())),
+                }
+            },
+            Expression::Try(target, throw) => match self.eval(ctx, *target) {
+                Ok(Value::Nil) if *throw => Err(EvalError::Unwrap),
+                Ok(value) => Ok(value),
+                Err(err) if *throw => Err(err),
+                Err(_) => Ok(Value::Nil),
+            },
+            Expression::Pipe(pipe) => self.eval_pipe(ctx, pipe),
+            Expression::Arrow(arrow, left, right) => self.eval_arrow(ctx, *arrow, *left, *right),
+            Expression::Sequence(statements) => {
+                let mut value = Value::Nil;
+                for statement in statements {
+                    value = self.eval(ctx, *statement)?;
+                    ctx.result = value.clone();
+                }
+                Ok(value)
+            },
+            Expression::Code(code) => Ok(Value::Closure(Closure::new(code.clone(), ctx).into())),
+            Expression::Def(def) => self.eval_def(ctx, def),
+            Expression::Error => Err(EvalError::Malformed),
+        }
+    }
+    
+    /// Evaluates a function call, optionally prepending a receiver to the positional arguments.
+    pub fn eval_call(
+        &mut self,
+        ctx: &mut Context,
+        call: &FnCall,
+        receiver: Option<Value>
+    ) -> Result<Value, EvalError> {
+        if receiver.is_none() {
+            if let Some(value) = self.eval_intrinsic(ctx, call)? {
+                return Ok(value);
+            }
+        }
+        
+        let mut args = Arguments::default();
+        args.pos_args.extend(receiver);
+        for arg in &call.pos_args {
+            let value = self.eval(ctx, *arg)?;
+            args.pos_args.push(value);
+        }
+        for (key, arg) in &call.nom_args {
+            let value = self.eval(ctx, *arg)?;
+            args.nom_args.insert(key.clone(), value);
+        }
+        
+        // Invoking a closure needs no help from the host.
+        if call.name == "call" {
+            if let Some(Value::Closure(closure)) = args.pos_args.first().cloned() {
+                args.pos_args.remove(0);
+                return closure.call(&mut *self.host, ctx, args);
+            }
+        }
+        
+        self.host.call(ctx, &call.name.to_str(), args)
+    }
+    
+    /// Evaluates the commands the interpreter handles by itself, returning `None` for all others.
+    /// 
+    /// These are the commands the parser synthesizes: `list`, `dict`, `if-then` and `if-else`;
+    /// and `use`, which imports every given namespace or command into the context.
+    fn eval_intrinsic(&mut self, ctx: &mut Context, call: &FnCall) -> Result<Option<Value>, EvalError> {
+        // Intrinsics are never namespaced.
+        let [name] = call.name.segments() else {
+            return Ok(None)
+        };
+        
+        Ok(Some(match name.as_str() {
+            "list" => {
+                let mut list = Vec::with_capacity(call.pos_args.len());
+                for arg in &call.pos_args {
+                    list.push(self.eval(ctx, *arg)?);
+                }
+                Value::List(list)
+            },
+            "dict" => {
+                let mut dict = FxHashMap::default();
+                for (key, arg) in &call.nom_args {
+                    dict.insert(key.clone(), self.eval(ctx, *arg)?);
+                }
+                Value::Dict(dict)
+            },
+            "if-then" if call.pos_args.len() == 2 => {
+                let condition = self.eval(ctx, call.pos_args[0])?;
+                if !condition.is_truthy() {
+                    return Ok(Some(condition));
+                }
+                let previous = std::mem::replace(&mut ctx.result, condition);
+                let value = self.eval(ctx, call.pos_args[1]);
+                ctx.result = previous;
+                value?
+            },
+            "use" if call.nom_args.is_empty() => {
+                for arg in &call.pos_args {
+                    match self.eval(ctx, *arg)? {
+                        Value::Str(path) => ctx.import(path),
+                        value => return Err(EvalError::ExpectButGot("a namespace or command".into(), value.get_type_str().into())),
+                    }
+                }
+                Value::Nil
+            },
+            "if-else" if call.pos_args.len() == 2 => {
+                match self.eval(ctx, call.pos_args[0]) {
+                    Ok(value) if value.is_truthy() => value,
+                    _ => self.eval(ctx, call.pos_args[1])?,
+                }
+            },
+            _ => return Ok(None)
+        }))
+    }
+    
+    /// Accesses a named field of the given value.
+    pub fn eval_field(&mut self, ctx: &mut Context, target: Value, name: &str) -> Result<Value, EvalError> {
+        match (&target, name) {
+            (Value::Dict(dict), name) => dict.get(name)
+                .cloned()
+                .ok_or_else(|| EvalError::NoSuchField(target.get_type_str(), name.into())),
+            (Value::Range(range), "start") => Ok(range.start.clone()),
+            (Value::Range(range), "end") => Ok(range.end.clone()),
+            (Value::Object(object), name) => object.field(name)
+                .ok_or_else(|| EvalError::NoSuchField(target.get_type_str(), name.into())),
+            _ => self.host.field(ctx, &target, name),
+        }
+    }
+    
+    /// Accesses an indexed element of the given value.
+    pub fn eval_index(&mut self, ctx: &mut Context, target: Value, index: Value) -> Result<Value, EvalError> {
+        let no_such_index = || EvalError::NoSuchIndex(target.get_type_str(), index.get_type_str());
+        match (&target, &index) {
+            (Value::List(list), Value::Int(i)) => {
+                let i = if *i < 0 { list.len() as i64 + *i } else { *i };
+                usize::try_from(i).ok()
+                    .and_then(|i| list.get(i))
+                    .cloned()
+                    .ok_or_else(no_such_index)
+            },
+            (Value::Dict(dict), Value::Str(key)) => dict.get(key)
+                .cloned()
+                .ok_or_else(no_such_index),
+            (Value::Str(str), Value::Int(i)) => usize::try_from(*i).ok()
+                .and_then(|i| str.chars().nth(i))
+                .map(|c| Value::Str(CompactString::from(c.encode_utf8(&mut [0; 4]))))
+                .ok_or_else(no_such_index),
+            (Value::Object(object), index) => object.index(index)
+                .ok_or_else(no_such_index),
+            _ => self.host.index(ctx, &target, &index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
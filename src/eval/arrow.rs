@@ -0,0 +1,105 @@
+//! Evaluation of arrows.
+
+use super::*;
+use crate::parser::Arrow;
+
+impl<'b, 'h> Interpreter<'b, 'h> {
+    /// Evaluates an arrow, passing the value of the left expression on to the right one.
+    /// 
+    /// See [`Arrow`] for the semantics of each kind of arrow.
+    pub fn eval_arrow(
+        &mut self,
+        ctx: &mut Context,
+        arrow: Arrow,
+        left: BlockRef,
+        right: BlockRef
+    ) -> Result<Value, EvalError> {
+        let value = self.eval(ctx, left)?;
+        match arrow {
+            Arrow::Assign => {
+                let name = self.target_variable(right)?;
+                ctx.locals.insert(name, value.clone());
+                Ok(value)
+            },
+            Arrow::Append => {
+                let name = self.target_variable(right)?;
+                match ctx.locals.entry(name).or_insert(Value::Nil) {
+                    Value::List(list) => list.push(value.clone()),
+                    slot @ Value::Nil => *slot = Value::List(vec![value.clone()]),
+                    other => return Err(EvalError::ExpectButGot("a list to append to".into(), other.get_type_str().into())),
+                }
+                Ok(value)
+            },
+            Arrow::Bind => self.eval_bound(ctx, right, value),
+            Arrow::Defer => {
+                self.deferred.push((right, value.clone()));
+                Ok(value)
+            },
+            Arrow::Grid => self.eval_bound(ctx, right, into_grid(value)),
+        }
+    }
+    
+    /// Evaluates the given expression with the value bound to `$`, restoring `$` afterwards.
+    pub(crate) fn eval_bound(&mut self, ctx: &mut Context, br: BlockRef, value: Value) -> Result<Value, EvalError> {
+        let previous = std::mem::replace(&mut ctx.result, value);
+        let result = self.eval(ctx, br);
+        ctx.result = previous;
+        result
+    }
+    
+    /// Evaluates every deferred expression, in the order they were deferred.
+    pub(crate) fn run_deferred(&mut self, ctx: &mut Context) -> Result<(), EvalError> {
+        while !self.deferred.is_empty() {
+            for (br, value) in std::mem::take(&mut self.deferred) {
+                self.eval_bound(ctx, br, value)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Returns the name of the variable the arrow targets.
+    fn target_variable(&self, br: BlockRef) -> Result<CompactString, EvalError> {
+        match self.block.get(br) {
+            Expression::Value(Literal::RefVar(name)) => Ok(name.clone()),
+            expr => Err(EvalError::ExpectButGot("a variable ($NAME)".into(), format!("{expr:?}").into())),
+        }
+    }
+}
+
+/// Arranges the given value as a grid, i.e. a list of rows, each being a list of cells.
+/// 
+/// - A list of dicts becomes a header row of their (sorted) keys, followed by one row per dict.
+/// - A list of lists is already a grid.
+/// - A list of anything else becomes one row per item.
+/// - A dict becomes one `[key value]`-row per entry, sorted by key.
+/// - Anything else becomes a single cell.
+pub fn into_grid(value: Value) -> Value {
+    let rows = match value {
+        Value::List(list) if list.iter().all(|v| matches!(v, Value::List(_))) => return Value::List(list),
+        Value::List(list) if !list.is_empty() && list.iter().all(|v| matches!(v, Value::Dict(_))) => {
+            let mut keys: Vec<CompactString> = list.iter()
+                .flat_map(|v| match v {
+                    Value::Dict(dict) => dict.keys().cloned().collect::<Vec<_>>(),
+                    _ => vec![],
+                })
+                .collect();
+            keys.sort();
+            keys.dedup();
+            
+            let mut rows = vec![Value::List(keys.iter().cloned().map(Value::Str).collect())];
+            for item in list {
+                let Value::Dict(mut dict) = item else { continue };
+                rows.push(Value::List(keys.iter().map(|key| dict.remove(key).unwrap_or(Value::Nil)).collect()));
+            }
+            rows
+        },
+        Value::List(list) => list.into_iter().map(|v| Value::List(vec![v])).collect(),
+        Value::Dict(dict) => {
+            let mut entries: Vec<_> = dict.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            entries.into_iter().map(|(k, v)| Value::List(vec![Value::Str(k), v])).collect()
+        },
+        value => vec![Value::List(vec![value])],
+    };
+    Value::List(rows)
+}
@@ -0,0 +1,106 @@
+//! Closures: quoted code, together with the variables it captured.
+
+use std::rc::Rc;
+
+use super::*;
+use crate::parser::{Code, Definition};
+use crate::registry::{BindError, Kind, Param, Signature};
+
+/// Quoted code evaluated into a value, capturing the local variables it refers to.
+/// 
+/// Created by evaluating an [`Expression::Code`]; exposed to scripts as a [`Value::Closure`].
+pub struct Closure {
+    /// The quoted code.
+    pub(crate) code: Rc<Code>,
+    /// The captured local variables.
+    pub(crate) captured: FxHashMap<CompactString, Value>,
+}
+
+impl Closure {
+    /// Creates a closure, capturing the variables the code refers to from the given context.
+    pub fn new(code: Rc<Code>, ctx: &Context) -> Self {
+        let captured = code.free_variables().into_iter()
+            .filter_map(|name| ctx.locals.get(&name).cloned().map(|value| (name, value)))
+            .collect();
+        Self { code, captured }
+    }
+    
+    /// Returns the names of the parameters.
+    pub fn params(&self) -> &[CompactString] {
+        &self.code.params
+    }
+    
+    /// Returns the value of a captured variable.
+    pub fn captured(&self, name: &str) -> Option<&Value> {
+        self.captured.get(name)
+    }
+    
+    /// Invokes the closure with the given arguments, dispatching commands to the host.
+    /// 
+    /// Arguments are bound to the parameters by name, or else by position; missing ones are `null`.
+    /// The first positional argument is also bound to `$`, even if there are no parameters.
+    /// The locals of the context are not visible to, nor changed by, the closure.
+    pub fn call(
+        &self,
+        host: &mut dyn CommandHost,
+        ctx: &mut Context,
+        args: Arguments
+    ) -> Result<Value, EvalError> {
+        let result = args.pos_args.first().cloned().unwrap_or_else(|| ctx.result.clone());
+        let mut locals = self.captured.clone();
+        let mut pos_args = args.pos_args.into_iter();
+        let mut nom_args = args.nom_args;
+        
+        for param in self.params() {
+            let value = nom_args.remove(param)
+                .or_else(|| pos_args.next())
+                .unwrap_or_default();
+            locals.insert(param.clone(), value);
+        }
+        
+        // Without parameters, a single argument may still be passed as `$`.
+        let extra = pos_args.len();
+        if extra > usize::from(self.params().is_empty()) {
+            return Err(BindError::Extra("closure".into(), self.params().len()).into());
+        }
+        if let Some(key) = nom_args.into_keys().min() {
+            return Err(BindError::Unknown("closure".into(), key).into());
+        }
+        
+        let locals = std::mem::replace(&mut ctx.locals, locals);
+        let result = std::mem::replace(&mut ctx.result, result);
+        let value = Interpreter::new(&self.code.body, host).run(ctx);
+        ctx.locals = locals;
+        ctx.result = result;
+        value
+    }
+}
+
+impl std::fmt::Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.code, f)
+    }
+}
+
+impl<'b, 'h> Interpreter<'b, 'h> {
+    /// Evaluates a definition, handing the defined command to the host.
+    /// 
+    /// Default values are evaluated once, right now.
+    pub fn eval_def(&mut self, ctx: &mut Context, def: &Definition) -> Result<Value, EvalError> {
+        let mut signature = Signature::new(def.name.to_string());
+        for param in &def.positional {
+            signature.positional.push(Param { name: param.clone(), kind: Kind::Any, default: None, flag: false });
+        }
+        for (param, default) in &def.named {
+            let default = match default {
+                Some(default) => Some(self.eval(ctx, *default)?),
+                None => None,
+            };
+            signature.named.push(Param { name: param.clone(), kind: Kind::Any, default, flag: false });
+        }
+        
+        let closure = Closure::new(def.code.clone(), ctx);
+        self.host.define(ctx, signature, closure.into())?;
+        Ok(Value::Nil)
+    }
+}
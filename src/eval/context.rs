@@ -0,0 +1,38 @@
+//! Evaluation context.
+
+use super::*;
+use crate::parser::CommandPath;
+
+/// The state an evaluation happens in.
+#[derive(Debug, Default, Clone)]
+pub struct Context {
+    /// The value of the context reference (`$$`).
+    pub context: Value,
+    
+    /// The value of the result reference (`$`).
+    pub result: Value,
+    
+    /// The local variables (`$NAME`).
+    pub locals: FxHashMap<CompactString, Value>,
+    
+    /// The namespaces and commands imported via `use`, in order.
+    pub imports: Vec<CommandPath>,
+}
+
+impl Context {
+    /// Creates a new context, with the given value as context reference (`$$`).
+    pub fn new(context: Value) -> Self {
+        Self {
+            context,
+            ..Default::default()
+        }
+    }
+    
+    /// Imports a namespace or command, making it available without its namespace.
+    pub fn import(&mut self, path: impl Into<CommandPath>) {
+        let path = path.into();
+        if !self.imports.contains(&path) {
+            self.imports.push(path);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Evaluation errors.
+
+use super::*;
+use thiserror::Error;
+
+/// An evaluation error.
+#[derive(Error, Debug)]
+pub enum EvalError {
+    /// The block has no entrypoint to start evaluation at.
+    #[error("The block has no entrypoint")]
+    NoEntry,
+    
+    /// The block contains an expression that failed to parse.
+    #[error("Cannot evaluate an expression that failed to parse")]
+    Malformed,
+    
+    /// A command is not known to the host.
+    #[error("Unknown command '{0}'")]
+    UnknownCommand(CompactString),
+    
+    /// A local variable was read before being set.
+    #[error("Undefined variable ${0}")]
+    UndefinedVariable(CompactString),
+    
+    /// A value does not have the requested field.
+    #[error("A {0} has no field '{1}'")]
+    NoSuchField(&'static str, CompactString),
+    
+    /// A value cannot be indexed with the given index.
+    #[error("A {0} cannot be indexed by this {1}")]
+    NoSuchIndex(&'static str, &'static str),
+    
+    /// A value cannot be iterated over.
+    #[error("A {0} cannot be iterated over")]
+    NotIterable(&'static str),
+    
+    /// Expected one thing, but got another.
+    #[error("Expected {0}, but got {1}")]
+    ExpectButGot(Cow<'static, str>, Cow<'static, str>),
+    
+    /// The host does not allow defining commands.
+    #[error("Cannot define command '{0}'")]
+    Undefinable(CompactString),
+    
+    /// Defined commands invoked each other too deeply.
+    #[error("Command '{0}' exceeded the recursion limit of {1}")]
+    RecursionLimit(CompactString, usize),
+    
+    /// A `null` value was unwrapped via `?!`.
+    #[error("Unwrapped a null value")]
+    Unwrap,
+    
+    /// The arguments of a command invocation did not match its signature.
+    #[error(transparent)]
+    Bind(#[from] crate::registry::BindError),
+    
+    /// A failure reported by a command.
+    #[error("{0}")]
+    Custom(Cow<'static, str>),
+}
@@ -0,0 +1,48 @@
+//! The interface between the interpreter and the host application.
+
+use std::rc::Rc;
+
+use super::*;
+use crate::registry::Signature;
+
+/// The evaluated arguments of a command invocation.
+#[derive(Debug, Default, Clone)]
+pub struct Arguments {
+    /// The positional arguments.
+    pub pos_args: Vec<Value>,
+    
+    /// The nominal/named arguments.
+    pub nom_args: FxHashMap<CompactString, Value>,
+}
+
+/// A host application that provides commands to the interpreter.
+pub trait CommandHost {
+    /// Invokes the command with the given name.
+    fn call(&mut self, ctx: &mut Context, name: &str, args: Arguments) -> Result<Value, EvalError>;
+    
+    /// Defines a command implemented by the given closure, via `def` or `alias`.
+    /// 
+    /// The closure takes every parameter of the signature by name.
+    fn define(&mut self, _ctx: &mut Context, signature: Signature, _closure: Rc<Closure>) -> Result<(), EvalError> {
+        Err(EvalError::Undefinable(signature.name))
+    }
+    
+    /// Accesses a named field of a value the interpreter can't handle by itself.
+    fn field(&mut self, _ctx: &mut Context, target: &Value, name: &str) -> Result<Value, EvalError> {
+        Err(EvalError::NoSuchField(target.get_type_str(), name.into()))
+    }
+    
+    /// Accesses an indexed element of a value the interpreter can't handle by itself.
+    fn index(&mut self, _ctx: &mut Context, target: &Value, index: &Value) -> Result<Value, EvalError> {
+        Err(EvalError::NoSuchIndex(target.get_type_str(), index.get_type_str()))
+    }
+}
+
+impl<F> CommandHost for F
+where
+    F: FnMut(&mut Context, &str, Arguments) -> Result<Value, EvalError>
+{
+    fn call(&mut self, ctx: &mut Context, name: &str, args: Arguments) -> Result<Value, EvalError> {
+        self(ctx, name, args)
+    }
+}
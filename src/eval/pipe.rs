@@ -0,0 +1,312 @@
+//! Lazy evaluation of pipes.
+
+use std::collections::hash_map;
+
+use super::*;
+use crate::parser::{Pipe, PipeSeg};
+
+impl<'b, 'h> Interpreter<'b, 'h> {
+    /// Evaluates a pipe, running every item of its source through all of its stages.
+    /// 
+    /// If the last stage finds, folds or collects, its result is returned as-is;
+    /// otherwise the items coming out of the pipe are collected into a list.
+    pub fn eval_pipe(&mut self, ctx: &mut Context, pipe: &'b Pipe) -> Result<Value, EvalError> {
+        let terminal = matches!(
+            pipe.stages.last(),
+            Some(PipeSeg::Finding { .. } | PipeSeg::Folding { .. } | PipeSeg::Collect { .. })
+        );
+        
+        let mut items = self.iter_pipe(ctx, pipe)?;
+        if terminal {
+            items.next().unwrap_or(Ok(Value::Nil))
+        } else {
+            items.collect::<Result<Vec<_>, _>>().map(Value::List)
+        }
+    }
+    
+    /// Evaluates the source of a pipe, returning an iterator that pulls items through its stages.
+    /// 
+    /// Every item is bound to `$` while a stage is evaluated;
+    /// the accumulator of a folding stage is bound to `$acc`.
+    /// Once the iterator is dropped, `$` is restored to its previous value.
+    pub fn iter_pipe<'a>(&'a mut self, ctx: &'a mut Context, pipe: &'b Pipe) -> Result<PipeIter<'a, 'b, 'h>, EvalError> {
+        let source = self.eval(ctx, pipe.source)?;
+        let source = into_items(source)?;
+        let previous = ctx.result.clone();
+        Ok(PipeIter {
+            interp: self,
+            ctx,
+            pipe,
+            source,
+            states: pipe.stages.iter().map(|_| StageState::Fresh).collect(),
+            previous,
+            failed: false,
+        })
+    }
+}
+
+/// The state of a single stage of a running pipe.
+enum StageState {
+    /// The stage is ready to pull the next item.
+    Fresh,
+    /// The stage is emitting the items of an iterable it produced.
+    Flatten(Items),
+    /// The stage will not emit any more items.
+    Done,
+}
+
+/// An iterator pulling the items of a pipe through its stages, one at a time.
+/// 
+/// Created via [`Interpreter::iter_pipe`].
+pub struct PipeIter<'a, 'b, 'h> {
+    interp: &'a mut Interpreter<'b, 'h>,
+    ctx: &'a mut Context,
+    pipe: &'b Pipe,
+    /// The items of the pipe source.
+    source: Items,
+    /// The state of every stage.
+    states: Vec<StageState>,
+    /// The value of `$` before the pipe started.
+    previous: Value,
+    /// Has any stage failed?
+    failed: bool,
+}
+
+impl PipeIter<'_, '_, '_> {
+    /// Evaluates the given expression with `$` bound to the given item.
+    fn eval_with(&mut self, item: Value, br: BlockRef) -> Result<Value, EvalError> {
+        self.ctx.result = item;
+        self.interp.eval(self.ctx, br)
+    }
+    
+    /// Pulls the next item that came through the first `depth` stages.
+    fn pull(&mut self, depth: usize) -> Option<Result<Value, EvalError>> {
+        let Some(index) = depth.checked_sub(1) else {
+            return self.source.next().map(Ok);
+        };
+        
+        match self.states[index] {
+            StageState::Done => return None,
+            StageState::Flatten(ref mut items) => match items.next() {
+                Some(item) => return Some(Ok(item)),
+                None => self.states[index] = StageState::Fresh,
+            },
+            StageState::Fresh => (),
+        }
+        
+        let result = match &self.pipe.stages[index] {
+            PipeSeg::Mapping { mapper } => loop {
+                let item = match self.pull(index)? {
+                    Ok(item) => item,
+                    Err(err) => break Err(err),
+                };
+                
+                match self.eval_with(item, *mapper) {
+                    // Lazy iterables are flattened into the pipe; lists and dicts are items of their own.
+                    Ok(value @ (Value::Range(_) | Value::Object(_))) => match into_items(value) {
+                        Ok(mut items) => if let Some(item) = items.next() {
+                            self.states[index] = StageState::Flatten(items);
+                            break Ok(item);
+                        },
+                        Err(err) => break Err(err),
+                    },
+                    result => break result,
+                }
+            },
+            PipeSeg::Exclude { predicate } => loop {
+                let item = match self.pull(index)? {
+                    Ok(item) => item,
+                    Err(err) => break Err(err),
+                };
+                
+                match self.eval_with(item.clone(), *predicate) {
+                    Ok(keep) if keep.is_truthy() => break Ok(item),
+                    Ok(_) => continue,
+                    Err(err) => break Err(err),
+                }
+            },
+            PipeSeg::Finding { predicate } => loop {
+                let item = match self.pull(index)? {
+                    Ok(item) => item,
+                    Err(err) => break Err(err),
+                };
+                
+                match self.eval_with(item.clone(), *predicate) {
+                    Ok(found) if found.is_truthy() => {
+                        // Short-circuit: nothing more is pulled from upstream.
+                        self.states[index] = StageState::Done;
+                        break Ok(item);
+                    },
+                    Ok(_) => continue,
+                    Err(err) => break Err(err),
+                }
+            },
+            PipeSeg::Folding { initial, reducer } => {
+                self.states[index] = StageState::Done;
+                self.fold(index, *initial, *reducer)
+            },
+            PipeSeg::Collect { collector } => {
+                self.states[index] = StageState::Done;
+                self.collect(index, *collector)
+            },
+        };
+        
+        Some(result)
+    }
+    
+    fn fold(&mut self, depth: usize, initial: BlockRef, reducer: BlockRef) -> Result<Value, EvalError> {
+        let mut acc = self.eval_with(Value::Nil, initial)?;
+        let previous = self.ctx.locals.remove("acc");
+        
+        let mut result = Ok(());
+        while let Some(item) = self.pull(depth) {
+            let item = match item {
+                Ok(item) => item,
+                Err(err) => {result = Err(err); break}
+            };
+            
+            self.ctx.locals.insert("acc".into(), std::mem::take(&mut acc));
+            acc = match self.eval_with(item, reducer) {
+                Ok(acc) => acc,
+                Err(err) => {result = Err(err); break}
+            };
+        }
+        
+        match previous {
+            Some(previous) => self.ctx.locals.insert("acc".into(), previous),
+            None => self.ctx.locals.remove("acc"),
+        };
+        
+        result.map(|_| acc)
+    }
+    
+    fn collect(&mut self, depth: usize, collector: BlockRef) -> Result<Value, EvalError> {
+        let receiver = self.eval_with(Value::Nil, collector)?;
+        
+        let mut error = None;
+        let items = std::iter::from_fn(|| match self.pull(depth)? {
+            Ok(item) => Some(item),
+            Err(err) => {error = Some(err); None}
+        });
+        
+        let collected = collect_into(receiver, items);
+        match error {
+            Some(err) => Err(err),
+            None => collected,
+        }
+    }
+}
+
+impl Iterator for PipeIter<'_, '_, '_> {
+    type Item = Result<Value, EvalError>;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        
+        let item = self.pull(self.states.len());
+        self.failed = matches!(item, Some(Err(_)));
+        item
+    }
+}
+
+impl Drop for PipeIter<'_, '_, '_> {
+    fn drop(&mut self) {
+        self.ctx.result = std::mem::take(&mut self.previous);
+    }
+}
+
+/// A lazy iterator over the items of a value, as pulled through a pipe.
+pub enum Items {
+    /// Exactly one item, or none once taken.
+    Once(Option<Value>),
+    /// The elements of a list.
+    List(std::vec::IntoIter<Value>),
+    /// The `[key value]`-pairs of a dict.
+    Dict(hash_map::IntoIter<CompactString, Value>),
+    /// The integers of an exclusive range.
+    Range(std::ops::Range<i64>),
+    /// The integers of an inclusive range.
+    RangeInclusive(std::ops::RangeInclusive<i64>),
+    /// The items of a host object.
+    Host(Box<dyn Iterator<Item = Value>>),
+}
+
+impl Iterator for Items {
+    type Item = Value;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Items::Once(item) => item.take(),
+            Items::List(list) => list.next(),
+            Items::Dict(dict) => dict.next().map(|(k, v)| Value::List(vec![Value::Str(k), v])),
+            Items::Range(range) => range.next().map(Value::Int),
+            Items::RangeInclusive(range) => range.next().map(Value::Int),
+            Items::Host(iter) => iter.next(),
+        }
+    }
+}
+
+/// Converts a value into the items a pipe iterates over, without materializing them.
+/// 
+/// Lists, dicts, integer ranges and iterable host objects yield their elements,
+/// `null` yields nothing, and every other value is a pipe of exactly one item.
+pub fn into_items(value: Value) -> Result<Items, EvalError> {
+    Ok(match value {
+        Value::Nil => Items::Once(None),
+        Value::List(list) => Items::List(list.into_iter()),
+        Value::Dict(dict) => Items::Dict(dict.into_iter()),
+        Value::Range(range) => match (&range.start, &range.end) {
+            (Value::Int(start), Value::Int(end)) => if range.inclusive {
+                Items::RangeInclusive(*start..=*end)
+            } else {
+                Items::Range(*start..*end)
+            },
+            _ => return Err(EvalError::NotIterable("non-integer range"))
+        },
+        Value::Object(object) => match object.iter() {
+            Some(iter) => Items::Host(iter),
+            None => Items::Once(Some(Value::Object(object))),
+        },
+        value => Items::Once(Some(value)),
+    })
+}
+
+/// Collects the given items into a receiver value.
+/// 
+/// Lists are extended, dicts take `[key value]`-pairs and strings get every item appended.
+pub fn collect_into(receiver: Value, items: impl IntoIterator<Item = Value>) -> Result<Value, EvalError> {
+    Ok(match receiver {
+        Value::List(mut list) => {
+            list.extend(items);
+            Value::List(list)
+        },
+        Value::Dict(mut dict) => {
+            for item in items {
+                match item {
+                    Value::List(pair) if pair.len() == 2 => {
+                        let mut pair = pair.into_iter();
+                        let (key, value) = (pair.next().unwrap(), pair.next().unwrap());
+                        let Value::Str(key) = key else {
+                            return Err(EvalError::ExpectButGot("a string key".into(), key.get_type_str().into()))
+                        };
+                        dict.insert(key, value);
+                    },
+                    item => return Err(EvalError::ExpectButGot("a [key value] pair".into(), item.get_type_str().into()))
+                }
+            }
+            Value::Dict(dict)
+        },
+        Value::Str(mut str) => {
+            for item in items {
+                match item {
+                    Value::Str(s) => str.push_str(&s),
+                    item => str.push_str(&format!("{item:?}")),
+                }
+            }
+            Value::Str(str)
+        },
+        receiver => return Err(EvalError::ExpectButGot("a list, dict or string to collect into".into(), receiver.get_type_str().into()))
+    })
+}
@@ -0,0 +1,159 @@
+//! Tests to ensure evaluation works.
+
+use super::*;
+
+/// A host that provides a tiny set of arithmetic commands.
+fn host(_ctx: &mut Context, name: &str, args: Arguments) -> Result<Value, EvalError> {
+    let ints = args.pos_args.iter().map(|v| match v {
+        Value::Int(i) => Ok(*i),
+        v => Err(EvalError::ExpectButGot("an integer".into(), v.get_type_str().into())),
+    });
+    
+    match name {
+        "+" => ints.sum::<Result<i64, _>>().map(Value::Int),
+        "*" => ints.product::<Result<i64, _>>().map(Value::Int),
+        "is_odd" => Ok(Value::Bool(ints.sum::<Result<i64, _>>()? % 2 == 1)),
+        "fail" => Err(EvalError::Custom("failed".into())),
+        "nothing" => Ok(Value::Nil),
+        "echo" => Ok(args.pos_args.into_iter().next().unwrap_or_default()),
+        _ => Err(EvalError::UnknownCommand(name.into())),
+    }
+}
+
+fn run(input: &str) -> Result<Value, EvalError> {
+    let block = crate::parse(input).expect("input should parse");
+    let mut ctx = Context::new(Value::Int(42));
+    eval(&block, &mut host, &mut ctx)
+}
+
+#[test]
+fn eval_values() -> Result<(), EvalError> {
+    assert_eq!(run("echo 1")?, Value::Int(1));
+    assert_eq!(run("echo $$")?, Value::Int(42));
+    assert_eq!(run("echo [1 2 3]")?, Value::List(vec![1.into(), 2.into(), 3.into()]));
+    assert_eq!(run("echo {a=1}.a")?, Value::Int(1));
+    assert_eq!(run("echo [1 2 3].[1]")?, Value::Int(2));
+    assert_eq!(run("echo (0..=9).end")?, Value::Int(9));
+    Ok(())
+}
+
+#[test]
+fn eval_commands() -> Result<(), EvalError> {
+    assert_eq!(run("+ 1 2 3")?, Value::Int(6));
+    assert_eq!(run("+ 1 (* 2 3)")?, Value::Int(7));
+    assert_eq!(run("= 1 + 2 * 3")?, Value::Int(7));
+    assert_eq!(run("= $x = 2 * 3")?, Value::Int(6));
+    assert_eq!(run("= (nothing) || 2 * 3 && 4")?, Value::Int(4));
+    assert_eq!(run("echo 2 -> $x")?, Value::Int(2));
+    assert_eq!(run("echo 3.(+ 4)")?, Value::Int(7));
+    assert!(matches!(run("unknown"), Err(EvalError::UnknownCommand(_))));
+    Ok(())
+}
+
+#[test]
+fn eval_fallible() -> Result<(), EvalError> {
+    assert_eq!(run("echo (fail)?")?, Value::Nil);
+    assert!(run("echo (fail)?!").is_err());
+    assert!(matches!(run("echo (nothing)?!"), Err(EvalError::Unwrap)));
+    assert_eq!(run("echo 1 && + $ 1")?, Value::Int(2));
+    assert_eq!(run("nothing && + $ 1")?, Value::Nil);
+    assert_eq!(run("fail || echo 3")?, Value::Int(3));
+    Ok(())
+}
+
+#[test]
+fn eval_pipes() -> Result<(), EvalError> {
+    assert_eq!(run("0..3 | + $ 1")?, Value::List(vec![1.into(), 2.into(), 3.into()]));
+    assert_eq!(run("list 0 1 2 3 4 |? is_odd $ |> list")?, Value::List(vec![1.into(), 3.into()]));
+    assert_eq!(run("0..=4 |! 0 + $ $acc")?, Value::Int(10));
+    assert_eq!(run("0..10 |?! is_odd $")?, Value::Int(1));
+    Ok(())
+}
+
+#[test]
+fn eval_lazy_pipes() -> Result<(), EvalError> {
+    // Only possible if the range is never materialized.
+    assert_eq!(run("0..9223372036854775807 |?! is_odd $")?, Value::Int(1));
+    assert_eq!(run("0..9223372036854775807 |?! is_odd $ | + $ 1")?, Value::List(vec![2.into()]));
+    assert_eq!(run("0..3 | 0..$ |> []")?, Value::List(vec![0.into(), 0.into(), 1.into()]));
+    assert_eq!(run("0..2 | list $ $")?, Value::List(vec![vec![0.into(), 0.into()].into(), vec![1.into(), 1.into()].into()]));
+    assert_eq!(run("list [a 1] [b 2] |> {}")?, run("echo {a=1 b=2}")?);
+    assert_eq!(run("list 1 2 |! 0 + $acc $ | * $ 2")?, Value::List(vec![6.into()]));
+    assert!(run("0..3 | fail").is_err());
+    assert_eq!(run("nothing | fail")?, Value::List(vec![]));
+    Ok(())
+}
+
+#[test]
+fn eval_scripts() -> Result<(), EvalError> {
+    let script = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("script should parse");
+        eval(&block, &mut host, &mut Context::default())
+    };
+    
+    assert_eq!(script("echo 1; + $ 1; * $ 3")?, Value::Int(6));
+    assert_eq!(script("echo 2\n+ $ 1\n\n* $ $")?, Value::Int(9));
+    assert_eq!(script("echo [1 2 3] |\n  + $ 1\n* 2 $.[2]")?, Value::Int(8));
+    assert_eq!(script("")?, Value::Nil);
+    Ok(())
+}
+
+#[test]
+fn eval_arrows() -> Result<(), EvalError> {
+    let script = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("script should parse");
+        let mut ctx = Context::default();
+        eval(&block, &mut host, &mut ctx).map(|value| (value, ctx))
+    };
+    
+    assert_eq!(run("echo 2 -> $x => + $ 1")?, Value::Int(3));
+    assert_eq!(script("echo 1 +> $l; echo 2 +> $l; echo $l")?.0, Value::List(vec![1.into(), 2.into()]));
+    assert!(script("echo 1 -> $l; echo 2 +> $l").is_err());
+    
+    // Deferred expressions run after everything else.
+    let (value, ctx) = script("echo 1 ~> echo $ +> $l; echo 2 +> $l")?;
+    assert_eq!(value, Value::Int(2));
+    assert_eq!(ctx.locals["l"], Value::List(vec![2.into(), 1.into()]));
+    
+    let grid = |rows: Vec<Vec<Value>>| Value::List(rows.into_iter().map(Value::List).collect());
+    assert_eq!(run("echo [{a=1 b=2} {a=3}] #> echo $")?, grid(vec![
+        vec!["a".into(), "b".into()],
+        vec![1.into(), 2.into()],
+        vec![3.into(), Value::Nil],
+    ]));
+    assert_eq!(run("echo {b=2 a=1} #> echo $")?, grid(vec![vec!["a".into(), 1.into()], vec!["b".into(), 2.into()]]));
+    assert_eq!(run("echo [1 2] #> echo $")?, grid(vec![vec![1.into()], vec![2.into()]]));
+    assert_eq!(run("echo 1 #> echo $")?, grid(vec![vec![1.into()]]));
+    Ok(())
+}
+
+#[test]
+fn eval_closures() -> Result<(), EvalError> {
+    let script = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("script should parse");
+        let mut ctx = Context::default();
+        eval(&block, &mut host, &mut ctx).map(|value| (value, ctx))
+    };
+    
+    assert_eq!(run("call {|$a $b| + $a $b} 1 2")?, Value::Int(3));
+    assert_eq!(run("call {|$a $b| + $a $b} 1 b=5")?, Value::Int(6));
+    assert_eq!(run("call {|| + $ 1} 2")?, Value::Int(3));
+    assert_eq!(run("echo {|$a| * $a 2}.(call 21)")?, Value::Int(42));
+    assert!(run("call {|$a| $a} 1 2").is_err());
+    assert!(run("call {|$a| $a} b=1").is_err());
+    
+    // Locals are captured when the closure is created, and never changed by it.
+    let (value, ctx) = script("echo 1 -> $x; echo {|$y| + $x $y; echo 5 -> $x} -> $f; echo 2 -> $x; call $f 10")?;
+    assert_eq!(value, Value::Int(5));
+    assert_eq!(ctx.locals["x"], Value::Int(2));
+    
+    // And may be invoked from Rust.
+    let Value::Closure(closure) = &ctx.locals["f"] else {
+        panic!("expected a closure")
+    };
+    assert_eq!(closure.params(), ["y"]);
+    assert_eq!(closure.captured("x"), Some(&Value::Int(1)));
+    let args = Arguments { pos_args: vec![Value::Int(3)], ..Default::default() };
+    assert_eq!(closure.call(&mut host, &mut Context::default(), args)?, Value::Int(5));
+    Ok(())
+}
@@ -5,6 +5,9 @@ use peekmore::*;
 pub mod input;
 pub use input::*;
 
+pub mod source;
+pub use source::*;
+
 pub mod tokenizer;
 pub use tokenizer::*;
 
@@ -20,5 +23,8 @@ pub use symbol::*;
 pub mod literal;
 pub use literal::*;
 
+pub mod error;
+pub use error::*;
+
 #[cfg(test)]
 mod tests;
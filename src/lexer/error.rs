@@ -0,0 +1,62 @@
+//! Lexer errors.
+
+use super::*;
+use thiserror::Error;
+
+/// A lexing error, with the byte-span of the offending input.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} at {span}")]
+pub struct LexError {
+    /// The kind of error.
+    pub kind: LexErrorKind,
+    
+    /// The span of the offending input.
+    pub span: Span,
+}
+
+/// The kind of a [`LexError`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LexErrorKind {
+    /// An `@` not followed by a valid object reference.
+    #[error("Invalid object reference")]
+    InvalidObjectReference,
+    
+    /// An integer that does not fit into 64 bits.
+    #[error("Integer '{0}' does not fit into 64 bits")]
+    IntegerOverflow(CompactString),
+    
+    /// A decimal number with invalid digits.
+    #[error("Invalid decimal '{0}'")]
+    InvalidDecimal(CompactString),
+    
+    /// An exponent that is missing or out of range.
+    #[error("Invalid exponent '{0}'")]
+    InvalidExponent(CompactString),
+    
+    /// A string without its closing quote.
+    #[error("Unterminated string")]
+    UnterminatedString,
+    
+    /// A numeric array without its closing bracket.
+    #[error("Unterminated numeric array")]
+    UnterminatedArray,
+    
+    /// A group whose opening delimiter is never closed.
+    #[error("Unclosed '{0}'")]
+    UnclosedDelimiter(Symbol),
+    
+    /// A closing delimiter without an opening one.
+    #[error("Unexpected '{0}'")]
+    UnexpectedDelimiter(Symbol),
+    
+    /// A group closed by the wrong delimiter.
+    #[error("Mismatched '{1}' for '{0}'")]
+    MismatchedDelimiter(Symbol, Symbol),
+}
+
+impl LexError {
+    /// Creates a new error of the given kind, spanning the given bytes.
+    pub fn new(kind: LexErrorKind, span: impl Into<Span>) -> Self {
+        Self { kind, span: span.into() }
+    }
+}
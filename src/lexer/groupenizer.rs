@@ -1,45 +1,133 @@
 //! The groupenizer takes a stream of tokens and converts it into a *tree* of token streams.
+//! 
+//! Delimiters that are unclosed, unexpected or mismatched are reported as [`TokenContent::Error`] tokens,
+//! replacing the group they belong to.
+//! 
+//! Angle brackets (`<`/`>`) are comparison operators, and thus never form groups.
+use std::iter::Peekable;
+
 use super::*;
 
 /// Find and stack groups from the given stream of tokens.
 pub fn groupenize<'it>(tokens: &'it mut impl TokenStream, delimiter: Option<Symbol>) -> Box<dyn TokenStream + 'it> {
+    let mut tokens = tokens.peekable();
+    let mut enclosing = Vec::from_iter(delimiter);
+    
     let iter = std::iter::from_fn(move || {
-        match tokens.next() {
-            Some(Token {
-                content: TokenContent::Symbol(
-                    symbol @ (
-                        Symbol::ParenLeft |
-                        Symbol::CurlyLeft |
-                        Symbol::BraketLeft
-                    )
-                ),
-                start,
-                end
-            }) => {
-                let group: Vec<Token> = groupenize(tokens, symbol.get_delimiter()).collect();
-                let end = group.last().map(|t| t.end).unwrap_or_else(|| end+1);
-                let group = TokenContent::Group(symbol, group);
-                //tokens.next();
-                Some(Token {
-                    content: group,
-                    start,
-                    end
-                })
-            },
-            Some(Token {
-                content: TokenContent::Symbol(
-                    symbol
-                ),
-                ..
-            }) if delimiter.map(|d| d == symbol).unwrap_or(false) => {
-                None // end of current group
-            },
-            Some(token) => Some(token),
-            
-            // TODO: Check for unmatched delimiters by `if let None = delimiter`
-            None => None, // natural end
-        }
+        let token = tokens.next()?;
+        Some(match group_start(&token) {
+            Some(symbol) => collect_group(&mut tokens, symbol, token, &mut enclosing),
+            None if is_delimiter(&token, delimiter) => return None, // end of current group
+            None if is_closer(&token) => unexpected_closer(token),
+            None => token,
+        })
     });
     
     Box::new(iter)
 }
+
+/// Checks if the token opens a group, returning its symbol.
+fn group_start(token: &Token) -> Option<Symbol> {
+    match token.content {
+        TokenContent::Symbol(
+            symbol @ (
+                Symbol::ParenLeft |
+                Symbol::CurlyLeft |
+                Symbol::BraketLeft
+            )
+        ) => Some(symbol),
+        _ => None
+    }
+}
+
+/// Checks if the token is the given delimiter.
+fn is_delimiter(token: &Token, delimiter: Option<Symbol>) -> bool {
+    matches!(token.content, TokenContent::Symbol(symbol) if delimiter == Some(symbol))
+}
+
+/// Checks if the token closes a group.
+fn is_closer(token: &Token) -> bool {
+    matches!(token.content, TokenContent::Symbol(symbol) if symbol.is_end_delimiter())
+}
+
+/// Turns a closing delimiter that closes no group into an error.
+fn unexpected_closer(token: Token) -> Token {
+    let TokenContent::Symbol(symbol) = token.content else {
+        return token
+    };
+    
+    Token {
+        content: TokenContent::Error(LexError::new(LexErrorKind::UnexpectedDelimiter(symbol), token.span())),
+        ..token
+    }
+}
+
+/// Collects the tokens of a group up to (and including) its closing delimiter.
+/// 
+/// The `enclosing` delimiters are those of the groups this group is nested in;
+/// encountering one of them before the own delimiter leaves this group unclosed.
+fn collect_group(
+    tokens: &mut Peekable<impl TokenStream>,
+    symbol: Symbol,
+    opener: Token,
+    enclosing: &mut Vec<Symbol>,
+) -> Token {
+    let delimiter = symbol.get_delimiter();
+    let mut group = Vec::new();
+    
+    let unclosed = || Token {
+        content: TokenContent::Error(LexError::new(LexErrorKind::UnclosedDelimiter(symbol), opener.span())),
+        ..opener.clone()
+    };
+    
+    loop {
+        let token = match tokens.peek() {
+            None => return unclosed(),
+            Some(token) if is_closer(token) && !is_delimiter(token, delimiter) => {
+                let found = match token.content {
+                    TokenContent::Symbol(found) => found,
+                    _ => unreachable!(),
+                };
+                
+                // Closes an enclosing group, which must not be consumed here.
+                if enclosing.contains(&found) {
+                    return unclosed()
+                }
+                
+                let span = token.span();
+                tokens.next();
+                return Token {
+                    content: TokenContent::Error(LexError::new(LexErrorKind::MismatchedDelimiter(symbol, found), span)),
+                    start: opener.start,
+                    end: span.end,
+                    start_lc: opener.start_lc,
+                    end_lc: span.end_lc,
+                }
+            },
+            Some(_) => tokens.next().unwrap(), // infallible
+        };
+        
+        if is_delimiter(&token, delimiter) {
+            // end of group, spanning the delimiter
+            return Token {
+                content: TokenContent::Group(symbol, group),
+                start: opener.start,
+                end: token.end,
+                start_lc: opener.start_lc,
+                end_lc: token.end_lc,
+            }
+        }
+        
+        let token = match group_start(&token) {
+            Some(symbol) => {
+                enclosing.extend(delimiter);
+                let token = collect_group(tokens, symbol, token, enclosing);
+                enclosing.pop();
+                token
+            },
+            None => token,
+        };
+        
+        group.push(token);
+    }
+}
@@ -28,17 +28,20 @@ impl Iterator for PosIter<'_> {
         
         self.lpos += 1;
         
+        let pos = PosChar {
+            char: current,
+            line: self.line,
+            col: self.lpos,
+            idx: index
+        };
+        
+        // The line-break is still part of the current line.
         if current == '\n' {
             self.line += 1;
             self.lpos = 0;
         }
         
-        Some(PosChar {
-            char: current,
-            line: self.line,
-            col: self.lpos,
-            idx: index
-        })
+        Some(pos)
     }
 }
 
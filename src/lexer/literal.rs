@@ -77,8 +77,25 @@ impl std::cmp::PartialEq for Literal {
 
 impl std::cmp::Eq for Literal {}
 
+impl std::hash::Hash for Literal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Nil | Self::RefRes | Self::RefCtx => (),
+            Self::Bool(v) => v.hash(state),
+            Self::Int(v) => v.hash(state),
+            // Just like `PartialEq`, by bits; `NaN` thus equals itself, while `0.0` and `-0.0` differ.
+            Self::Dec(v) => v.to_bits().hash(state),
+            Self::Uid(v) | Self::ObjUid(v) => v.hash(state),
+            Self::Str(v) | Self::RefVar(v) | Self::ObjKey(v) => v.hash(state),
+            Self::Byt(v) => v.hash(state),
+            Self::ObjIdx(v) => v.hash(state),
+        }
+    }
+}
+
 /// A possibly-typed buffer of bytes.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Byt {
     /// The type of the data, or an empty string.
     pub kind: CompactString,
@@ -140,7 +157,7 @@ impl std::fmt::Debug for Literal {
 }
 
 /// Format the given string as bareword, if possible.
-pub fn bareword_format(input: &str) -> Cow<str> {
+pub fn bareword_format(input: &str) -> Cow<'_, str> {
     for (i, ch) in input.char_indices() {
         if i == 0 {
             if is_bareword_start(ch) {
@@ -0,0 +1,168 @@
+//! Mapping of byte-positions in some source to lines and columns.
+
+use std::ops::Range;
+
+/// A line and column in some source, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineCol {
+    /// The line-number.
+    pub line: usize,
+    /// The column, counted in characters.
+    pub col: usize,
+}
+
+impl Default for LineCol {
+    fn default() -> Self {
+        Self { line: 1, col: 1 }
+    }
+}
+
+impl std::fmt::Display for LineCol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// A span of bytes in some source, together with the lines and columns of its ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte-position of the START of the span.
+    pub start: usize,
+    /// Byte-position of the END of the span.
+    pub end: usize,
+    /// Line and column of the START of the span.
+    pub start_lc: LineCol,
+    /// Line and column of the END of the span.
+    pub end_lc: LineCol,
+}
+
+impl Span {
+    /// Returns the byte-range of the span.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+    
+    /// Returns a span covering both this and the other span.
+    pub fn join(self, other: Span) -> Span {
+        let (start, start_lc) = if other.start < self.start {(other.start, other.start_lc)} else {(self.start, self.start_lc)};
+        let (end, end_lc) = if other.end > self.end {(other.end, other.end_lc)} else {(self.end, self.end_lc)};
+        Span { start, end, start_lc, end_lc }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    /// Creates a span from a byte-range, without line and column information.
+    fn from(range: Range<usize>) -> Self {
+        Span {
+            start: range.start,
+            end: range.end,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.range()
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start_lc)
+    }
+}
+
+/// Converts byte-positions in a source into lines and columns, and back.
+#[derive(Debug, Clone)]
+pub struct SourceMap<'s> {
+    /// The name of the source, usually a file-name.
+    name: Option<&'s str>,
+    /// The source itself.
+    source: &'s str,
+    /// Byte-positions of the start of every line.
+    lines: Vec<usize>,
+}
+
+impl<'s> SourceMap<'s> {
+    /// Creates a new source map for the given source.
+    pub fn new(source: &'s str) -> Self {
+        let lines = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { name: None, source, lines }
+    }
+    
+    /// Sets the name of the source, as used by [`SourceMap::location`].
+    #[must_use]
+    pub fn with_name(mut self, name: &'s str) -> Self {
+        self.name = Some(name);
+        self
+    }
+    
+    /// Returns the source.
+    pub fn source(&self) -> &'s str {
+        self.source
+    }
+    
+    /// Returns the number of lines in the source.
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+    
+    /// Returns the given line, without its line-break.
+    pub fn line(&self, line: usize) -> Option<&'s str> {
+        let start = *self.lines.get(line.checked_sub(1)?)?;
+        let end = self.lines.get(line).map(|e| e - 1).unwrap_or(self.source.len());
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+    
+    /// Converts a byte-position into a line and column.
+    /// 
+    /// Positions past the end of the source are clamped to its end.
+    pub fn locate(&self, offset: usize) -> LineCol {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        
+        let line = self.lines.partition_point(|start| *start <= offset);
+        let start = self.lines[line - 1];
+        LineCol {
+            line,
+            col: self.source[start..offset].chars().count() + 1,
+        }
+    }
+    
+    /// Converts a line and column back into a byte-position, if it is within the source.
+    pub fn offset(&self, lc: LineCol) -> Option<usize> {
+        let start = *self.lines.get(lc.line.checked_sub(1)?)?;
+        let line = &self.source[start..];
+        let line = &line[..line.find('\n').map(|e| e + 1).unwrap_or(line.len())];
+        
+        let col = lc.col.checked_sub(1)?;
+        match line.char_indices().nth(col) {
+            Some((i, _)) => Some(start + i),
+            None if line.chars().count() == col => Some(start + line.len()),
+            None => None,
+        }
+    }
+    
+    /// Converts a byte-range into a [`Span`].
+    pub fn span(&self, range: Range<usize>) -> Span {
+        Span {
+            start_lc: self.locate(range.start),
+            end_lc: self.locate(range.end),
+            start: range.start,
+            end: range.end,
+        }
+    }
+    
+    /// Formats a byte-position as `name:line:col`, or `line:col` if the source has no name.
+    pub fn location(&self, offset: usize) -> String {
+        match self.name {
+            Some(name) => format!("{name}:{}", self.locate(offset)),
+            None => self.locate(offset).to_string(),
+        }
+    }
+}
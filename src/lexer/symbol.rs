@@ -235,7 +235,7 @@ impl Symbol {
         )
     }
     
-    /// Is the symbol a postfix operator?
+    /// Is the symbol a infix operator?
     pub fn is_infix_operator(&self) -> bool {
         matches! {self
             , Self::Plus
@@ -244,6 +244,32 @@ impl Symbol {
             | Self::Slash
             | Self::Percentage
             | Self::DoubleStar
+            | Self::EqEq
+            | Self::NotEq
+            | Self::AngleLeft
+            | Self::AngleRight
+            | Self::EqLess
+            | Self::EqGreater
+            | Self::Compare
+            | Self::DoubleAmpersand
+            | Self::DoublePipe
+            | Self::EqualSign
+        }
+    }
+    
+    /// Is the symbol a right-associative infix operator?
+    pub fn is_right_associative(&self) -> bool {
+        matches! {self
+            , Self::DoubleStar
+            | Self::EqualSign
+        }
+    }
+    
+    /// Is the symbol a prefix operator?
+    pub fn is_prefix_operator(&self) -> bool {
+        matches! {self
+            , Self::Dash
+            | Self::ExclamationMark
         }
     }
     
@@ -270,6 +296,8 @@ impl Symbol {
     }
     
     /// Is the symbol a delimiter?
+    /// 
+    /// Angle brackets are not, as they are comparison operators.
     pub fn is_delimiter(&self) -> bool {
         self.is_start_delimiter() || self.is_end_delimiter()
     }
@@ -280,7 +308,6 @@ impl Symbol {
             , Self::ParenLeft
             | Self::BraketLeft
             | Self::CurlyLeft
-            | Self::AngleLeft
         )
     }
     
@@ -290,7 +317,6 @@ impl Symbol {
             , Self::ParenRight
             | Self::BraketRight
             | Self::CurlyRight
-            | Self::AngleRight
         )
     }
     
@@ -300,7 +326,6 @@ impl Symbol {
             Self::ParenLeft => Some(Self::ParenRight),
             Self::BraketLeft => Some(Self::BraketRight),
             Self::CurlyLeft => Some(Self::CurlyRight),
-            Self::AngleLeft => Some(Self::AngleRight),
             _ => None
         }
     }
@@ -308,8 +333,14 @@ impl Symbol {
     /// Returns the precendence for this symbol.
     pub fn get_precedence(&self) -> Precedence {
         match self {
-            // ASSIGN => 1,
-            // CONDITION => 2,
+            // ASSIGN
+            Self::EqualSign => Precedence::Assignment,
+            
+            // CONDITION
+            Self::DoublePipe => Precedence::Or,
+            Self::DoubleAmpersand => Precedence::And,
+            Self::EqEq | Self::NotEq => Precedence::Equality,
+            Self::AngleLeft | Self::AngleRight | Self::EqLess | Self::EqGreater | Self::Compare => Precedence::Comparison,
             
             // SUM
             Self::Plus | Self::Dash => Precedence::Sum,
@@ -317,15 +348,16 @@ impl Symbol {
             // PRODUCT
             Self::Star | Self::Slash | Self::Percentage => Precedence::Product,
             
+            // PREFIX => 8,
+            
             // EXPONENT
             Self::DoubleStar => Precedence::Exponent,
             
-            // PREFIX => 6,
-            // POSTFIX => 7,
-            Self::Dot | Self::Range | Self::QuestionMark | Self::Tilde | Self::ThinArrow => Precedence::Postfix,
+            // POSTFIX
+            Self::Dot | Self::Range | Self::QuestionMark | Self::Tilde => Precedence::Postfix,
             _ if self.is_postop().is_some() => Precedence::Postfix,
             
-            // CALL & GROUP => 8,
+            // CALL & GROUP => 11,
             // Self::ParenLeft => Precedence::Call,
             // Self::BraketLeft => Precedence::Call,
             // Self::CurlyLeft => Precedence::Call,
@@ -363,21 +395,52 @@ pub enum Precedence {
     /// Assignment of operands.
     Assignment = 1,
     
+    /// Logical disjunction of operands.
+    Or = 2,
+    
+    /// Logical conjunction of operands.
+    And = 3,
+    
+    /// Equality of operands.
+    Equality = 4,
+    
+    /// Comparison of operands.
+    Comparison = 5,
+    
     /// Summation of operands.
-    Sum = 3,
+    Sum = 6,
     
     /// Product of operands.
-    Product = 4,
-    
-    /// Exponent of operands.
-    Exponent = 5,
+    Product = 7,
     
     /// Prefix operands.
-    Prefix = 6,
+    Prefix = 8,
+    
+    /// Exponent of operands; binds tighter than prefix operators, so that `-2 ** 2` is `-(2 ** 2)`.
+    Exponent = 9,
     
     /// Postfix operands.
-    Postfix = 7,
+    Postfix = 10,
     
     /// Call operands.
-    Call = 8,
+    Call = 11,
+}
+
+impl Precedence {
+    /// Returns the next-lower precedence, used to parse the right operand of right-associative operators.
+    pub fn lower(self) -> Self {
+        match self {
+            Self::Null | Self::Assignment => Self::Null,
+            Self::Or => Self::Assignment,
+            Self::And => Self::Or,
+            Self::Equality => Self::And,
+            Self::Comparison => Self::Equality,
+            Self::Sum => Self::Comparison,
+            Self::Product => Self::Sum,
+            Self::Prefix => Self::Product,
+            Self::Exponent => Self::Prefix,
+            Self::Postfix => Self::Exponent,
+            Self::Call => Self::Postfix,
+        }
+    }
 }
@@ -34,3 +34,124 @@ fn lex_numbers() {
 fn lex_example() {
     tokenize("blocks (b box 0 0 0 15 15 15) set air").inspect(|t| println!("{:?}",t)).count();
 }
+
+#[test]
+fn lex_errors() {
+    use super::{LexErrorKind, TokenContent};
+    
+    let error = |input: &str| tokenize(input)
+        .find_map(|t| match t.content {
+            TokenContent::Error(err) => Some(err),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("lexing {input:?} should fail"));
+    
+    assert_eq!(error("@").kind, LexErrorKind::InvalidObjectReference);
+    assert_eq!(error("@ foo").span.range(), 0..1);
+    assert_eq!(error("99999999999999999999").kind, LexErrorKind::IntegerOverflow("99999999999999999999".into()));
+    assert_eq!(error("foo -0x8000000000000000 bar").span.range(), 4..23);
+    assert!(matches!(error("0[1 99999999999999999999]").kind, LexErrorKind::IntegerOverflow(_)));
+    assert!(matches!(error("1e99").kind, LexErrorKind::IntegerOverflow(_)));
+    assert!(matches!(error("1e").kind, LexErrorKind::InvalidExponent(_)));
+    assert!(matches!(error("1e99999999999").kind, LexErrorKind::InvalidExponent(_)));
+    assert_eq!(error("foo \"bar").kind, LexErrorKind::UnterminatedString);
+    assert_eq!(error("foo 'bär").span.range(), 4..9);
+    assert_eq!(error("@'bar").kind, LexErrorKind::UnterminatedString);
+}
+
+#[test]
+fn lex_exponents() {
+    use super::{Literal, TokenContent};
+    
+    let literal = |input: &str| match tokenize(input).next().map(|t| t.content) {
+        Some(TokenContent::Literal(l)) => l,
+        t => panic!("lexing {input:?} should yield a literal, but got {t:?}"),
+    };
+    
+    assert_eq!(literal("1e3"), Literal::Int(1000));
+    assert_eq!(literal("-2e2"), Literal::Int(-200));
+    assert_eq!(literal("1e-3"), Literal::Dec(0.001));
+    assert_eq!(literal("0.5e1"), Literal::Dec(5.0));
+}
+
+#[test]
+fn source_map() {
+    use super::{LineCol, SourceMap};
+    
+    let map = SourceMap::new("foo\nbär baz\r\n\nqux").with_name("test.ifn");
+    assert_eq!(map.line_count(), 4);
+    assert_eq!(map.line(2), Some("bär baz"));
+    assert_eq!(map.line(3), Some(""));
+    assert_eq!(map.line(5), None);
+    
+    assert_eq!(map.locate(0), LineCol { line: 1, col: 1 });
+    assert_eq!(map.locate(3), LineCol { line: 1, col: 4 });
+    assert_eq!(map.locate(4), LineCol { line: 2, col: 1 });
+    assert_eq!(map.locate(9), LineCol { line: 2, col: 5 });
+    assert_eq!(map.locate(17), LineCol { line: 4, col: 3 });
+    assert_eq!(map.locate(99), LineCol { line: 4, col: 4 });
+    
+    for offset in [0, 3, 4, 5, 7, 9, 14, 15, 16, 18] {
+        assert_eq!(map.offset(map.locate(offset)), Some(offset));
+    }
+    assert_eq!(map.offset(LineCol { line: 1, col: 9 }), None);
+    assert_eq!(map.location(9), "test.ifn:2:5");
+}
+
+#[test]
+fn lex_line_cols() {
+    use super::LineCol;
+    
+    let tokens: Vec<_> = tokenize("foo\n  (bar)\n'baz\nqux'").collect();
+    let lcs: Vec<_> = tokens.iter()
+        .map(|t| ((t.start_lc.line, t.start_lc.col), (t.end_lc.line, t.end_lc.col)))
+        .collect();
+    assert_eq!(lcs, [((1, 1), (1, 4)), ((2, 3), (2, 4)), ((2, 4), (2, 7)), ((2, 7), (2, 8)), ((3, 1), (4, 5))]);
+    assert_eq!(tokens[1].span().start_lc, LineCol { line: 2, col: 3 });
+}
+
+#[test]
+fn group_delimiters() {
+    use peekmore::PeekMore;
+    use super::{groupenize, LexErrorKind, Symbol, Token, TokenContent};
+    
+    fn flatten(tokens: Vec<Token>) -> Vec<Token> {
+        tokens.into_iter().flat_map(|t| match t.content {
+            TokenContent::Group(_, group) => flatten(group),
+            _ => vec![t],
+        }).collect()
+    }
+    
+    let groups = |input: &str| groupenize(&mut tokenize(input).peekmore(), None).collect::<Vec<Token>>();
+    let error = |input: &str| flatten(groups(input)).into_iter()
+        .find_map(|t| match t.content {
+            TokenContent::Error(err) => Some(err),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("groupenizing {input:?} should fail"));
+    
+    for input in ["foo (bar [baz] {a=1})", "< $.health 50", "= 1 < 2 > 3", "[1 2] |> list", "(foo > bar)", "= $x <10", "foo <bar"] {
+        assert!(flatten(groups(input)).iter().all(|t| !matches!(t.content, TokenContent::Error(_))), "groupenizing {input:?} should succeed");
+    }
+    
+    // Angle brackets are operators, not delimiters.
+    let tokens = groups("foo <bar baz> qux");
+    assert_eq!(tokens.len(), 6);
+    assert!(matches!(tokens[1].content, TokenContent::Symbol(Symbol::AngleLeft)));
+    
+    let err = error("foo (bar baz");
+    assert_eq!(err.kind, LexErrorKind::UnclosedDelimiter(Symbol::ParenLeft));
+    assert_eq!(err.span.range(), 4..5);
+    
+    let err = error("foo bar)");
+    assert_eq!(err.kind, LexErrorKind::UnexpectedDelimiter(Symbol::ParenRight));
+    assert_eq!(err.span.range(), 7..8);
+    
+    let err = error("(foo ]");
+    assert_eq!(err.kind, LexErrorKind::MismatchedDelimiter(Symbol::ParenLeft, Symbol::BraketRight));
+    assert_eq!(err.span.range(), 5..6);
+    
+    let err = error("[foo (bar] baz");
+    assert_eq!(err.kind, LexErrorKind::UnclosedDelimiter(Symbol::ParenLeft));
+    assert_eq!(err.span.range(), 5..6);
+}
@@ -1,6 +1,6 @@
 //! Token representation.
 
-use super::{Literal, Symbol, Precedence};
+use super::{Literal, LexError, LineCol, Span, Symbol, Precedence};
 
 /// An individual token.
 #[derive(Debug, Clone)]
@@ -11,11 +11,27 @@ pub struct Token {
     /// Byte-position of the END of the token in the input string slice.
     pub end: usize,
     
+    /// Line and column of the START of the token.
+    pub start_lc: LineCol,
+    
+    /// Line and column of the END of the token.
+    pub end_lc: LineCol,
+    
     /// The content of the token.
     pub content: TokenContent
 }
 
 impl Token {
+    /// Returns the span of the token.
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.end,
+            start_lc: self.start_lc,
+            end_lc: self.end_lc,
+        }
+    }
+    
     /// Returns the precendence for this token, or 0.
     pub fn get_precedence(&self) -> Precedence {
         match self.get_symbol() {
@@ -31,13 +47,14 @@ impl Token {
             TokenContent::Literal(_) => None,
             TokenContent::Group(s, _) => Some(s),
             TokenContent::Remainder(_) => None,
+            TokenContent::Error(_) => None,
         }
     }
 }
 
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?} at {}", self.content, self.start)
+        write!(f, "{:?} at {}", self.content, self.start_lc)
     }
 }
 
@@ -46,6 +63,8 @@ impl From<(usize, usize, Symbol)> for Token {
         Token {
             start: src.0,
             end: src.1,
+            start_lc: Default::default(),
+            end_lc: Default::default(),
             content: TokenContent::Symbol(src.2)
         }
     }
@@ -56,6 +75,8 @@ impl From<(usize, usize, Literal)> for Token {
         Token {
             start: src.0,
             end: src.1,
+            start_lc: Default::default(),
+            end_lc: Default::default(),
             content: TokenContent::Literal(src.2)
         }
     }
@@ -66,6 +87,8 @@ impl From<(usize, usize, TokenContent)> for Token {
         Token {
             start: src.0,
             end: src.1,
+            start_lc: Default::default(),
+            end_lc: Default::default(),
             content: src.2
         }
     }
@@ -93,6 +116,9 @@ pub enum TokenContent {
     
     /// The remainder.
     Remainder(String),
+    
+    /// Input that could not be lexed.
+    Error(LexError),
 }
 
 impl TryFrom<&TokenContent> for Symbol {
@@ -104,6 +130,7 @@ impl TryFrom<&TokenContent> for Symbol {
             TokenContent::Literal(_) => Err(()),
             TokenContent::Group(_, _) => Err(()),
             TokenContent::Remainder(_) => Err(()),
+            TokenContent::Error(_) => Err(()),
         }
     }
 }
@@ -16,6 +16,8 @@ impl<T> TokenStream for T where T: Iterator<Item = Token> {}
 
 /// A [`Iterator`] of plain/linear [`Token`]s from some input [`str`].
 pub struct LinearTokenIter<'i> {
+    input: &'i str,
+    map: SourceMap<'i>,
     source: PeekMoreIterator<PosIter<'i>>,
     symbuf: [u8; 2 * std::mem::size_of::<char>()],
 }
@@ -24,16 +26,65 @@ impl<'i> LinearTokenIter<'i> {
     /// Creates a new stream of plain/linear tokens.
     pub fn new(source: &'i str) -> Self {
         Self {
+            input: source,
+            map: SourceMap::new(source),
             source: PosIter::from(source.char_indices()).peekmore(),
             symbuf: [0; std::mem::size_of::<char>() * 2]
         }
     }
+    
+    /// Fills in the line and column of the token, and of anything within it.
+    fn locate(&self, token: &mut Token) {
+        token.start_lc = self.map.locate(token.start);
+        token.end_lc = self.map.locate(token.end);
+        match &mut token.content {
+            TokenContent::Group(_, tokens) => tokens.iter_mut().for_each(|t| self.locate(t)),
+            TokenContent::Error(err) => err.span = self.map.span(err.span.range()),
+            _ => ()
+        }
+    }
+    
+    /// Returns the byte-position of the next character, or the end of the input.
+    fn offset(&mut self) -> usize {
+        match self.source.peek() {
+            Some(c) => c.idx,
+            None => self.input.len(),
+        }
+    }
+    
+    /// Creates an error-token of the given kind, spanning from `start` to the next character.
+    fn error(&mut self, start: usize, kind: LexErrorKind) -> Token {
+        let end = self.offset();
+        (start, end, TokenContent::Error(LexError::new(kind, start..end))).into()
+    }
+    
+    /// Creates an overflow error-token, spanning from `start` to the next character.
+    fn overflow(&mut self, start: usize) -> Token {
+        let end = self.offset();
+        let number = self.input[start..end].into();
+        self.error(start, LexErrorKind::IntegerOverflow(number))
+    }
 }
 
 impl<'i> Iterator for LinearTokenIter<'i> {
     type Item = Token;
     
     fn next(&mut self) -> Option<Self::Item> {
+        let mut token = self.lex()?;
+        
+        // Every token ends right before the next unconsumed character.
+        if !matches!(token.content, TokenContent::Remainder(_)) {
+            token.end = self.offset();
+        }
+        
+        self.locate(&mut token);
+        Some(token)
+    }
+}
+
+impl<'i> LinearTokenIter<'i> {
+    /// Lexes the next token, with a possibly inexact end.
+    fn lex(&mut self) -> Option<Token> {
         // Skip any and all whitespace...
         let current = loop {
             match self.source.next() {
@@ -47,7 +98,6 @@ impl<'i> Iterator for LinearTokenIter<'i> {
         };
         
         let index = current.idx;
-        let mut last_idx = current.idx;
         
         // Turn both the current-char and the current-with-next-char into string slices.
         let (currstr, peekstr) = {
@@ -104,22 +154,17 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                         return Some((start, end, Literal::ObjKey(bareword)).into());
                     }
                     
-                    // Check for start of double-quoted string...
-                    if char == '"' {
-                        self.source.next(); // drop start
-                        let (start, end, string) = try_lex_string(&mut self.source, index, char, '"');
-                        return Some((start, end, Literal::ObjKey(string)).into());
-                    }
-                    
-                    // Check for start of single-quoted string...
-                    if char == '\'' {
+                    // Check for start of double-quoted or single-quoted string...
+                    if char == '"' || char == '\'' {
                         self.source.next(); // drop start
-                        let (start, end, string) = try_lex_string(&mut self.source, index, char, '\'');
-                        return Some((start, end, Literal::ObjKey(string)).into());
+                        return Some(match try_lex_string(&mut self.source, index, char, char) {
+                            Ok((start, end, string)) => (start, end, Literal::ObjKey(string)).into(),
+                            Err(err) => (err.span.start, err.span.end, TokenContent::Error(err)).into(),
+                        });
                     }
                 }
                 
-                panic!("Failed to parse object reference (@) at {}:{}", current.line, current.col);
+                return Some(self.error(index, LexErrorKind::InvalidObjectReference));
             }
             
             if symbol == Symbol::DollarSign {
@@ -162,16 +207,12 @@ impl<'i> Iterator for LinearTokenIter<'i> {
             ).into());
         }
         
-        // Check for start of double-quoted string...
-        if *current == '"' {
-            let (start, end, string) = try_lex_string(&mut self.source, index, *current, '"');
-            return Some((start, end, Literal::Str(string)).into());
-        }
-        
-        // Check for start of single-quoted string...
-        if *current == '\'' {
-            let (start, end, string) = try_lex_string(&mut self.source, index, *current, '\'');
-            return Some((start, end, Literal::Str(string)).into());
+        // Check for start of double-quoted or single-quoted string...
+        if *current == '"' || *current == '\'' {
+            return Some(match try_lex_string(&mut self.source, index, *current, *current) {
+                Ok((start, end, string)) => (start, end, Literal::Str(string)).into(),
+                Err(err) => (err.span.start, err.span.end, TokenContent::Error(err)).into(),
+            });
         }
         
         // NOTE: This is the worst code of this lexer!
@@ -222,14 +263,19 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                     let mut array: Vec<Token> = vec![]; // TODO: Make this an i64-vec
                     
                     loop {
-                        if let Some(PosChar { char: ']', idx: index , .. }) = self.source.peek().copied() {
-                            last_idx = index;
+                        if let Some(PosChar { char: ']', .. }) = self.source.peek().copied() {
                             self.source.next(); // eat ]
                             break; // end of array
                         }
                         
+                        if self.source.peek().is_none() {
+                            return Some(self.error(index, LexErrorKind::UnterminatedArray));
+                        }
+                        
                         buffer.clear();
                         
+                        let start = self.offset();
+                        let mut end = start;
                         let mut sign = false;
                         
                         if let Some(PosChar { char: '-', .. }) = self.source.peek().copied() {
@@ -243,7 +289,6 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                         // Eat all the INTEGER digits...
                         while let Some(PosChar { char: peeked, idx: index , .. }) = self.source.peek().copied() {
                             if peeked == ']' { break }
-                            last_idx = index;
                             
                             if ! is_digit_valid(peeked, radix) {
                                 self.source.next(); // eat unknown
@@ -252,6 +297,7 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                             
                             buffer.push(peeked);
                             self.source.next(); // eat digit
+                            end = index + peeked.len_utf8();
                         }
                         
                         if buffer.is_empty() {
@@ -260,17 +306,14 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                         
                         let integer = match i64::from_str_radix(&buffer, radix) {
                             Ok(i) => i,
-                            Err(err) => {
-                                panic!("Failed to parse '{}' with radix {}: {}", buffer, radix, err)
-                                //return Some((index, last_idx, TokenContent::Remainder(buffer.into())).into());
-                            },
+                            Err(_) => return Some(self.overflow(index)),
                         };
                         
                         let integer = if sign { -integer } else { integer };
-                        array.push((last_idx, last_idx, Literal::Int(integer)).into());
+                        array.push((start, end, Literal::Int(integer)).into());
                     }
                     
-                    return Some((index, last_idx, TokenContent::Group(Symbol::BraketLeft, array)).into());
+                    return Some((index, index, TokenContent::Group(Symbol::BraketLeft, array)).into());
                 }
             }
             
@@ -286,7 +329,7 @@ impl<'i> Iterator for LinearTokenIter<'i> {
             
             let integer = match i64::from_str_radix(&buffer, radix) {
                 Ok(i) => i,
-                Err(err) => panic!("Failed to parse '{}' with radix {}: {}", buffer, radix, err),
+                Err(_) => return Some(self.overflow(index)),
             };
             
             let decimal = if radix == 10
@@ -312,12 +355,15 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                     }
                 }
                 
-                buffer.parse().unwrap()
+                match buffer.parse() {
+                    Ok(decimal) => decimal,
+                    Err(_) => return Some(self.error(index, LexErrorKind::InvalidDecimal(buffer))),
+                }
             } else {
                 0f64
             };
             
-            let pow10: f64 = if radix == 10 && 'e' == self.source.peek().copied().map(|c|c.char).unwrap_or(' ') {
+            let exponent: i32 = if radix == 10 && 'e' == self.source.peek().copied().map(|c|c.char).unwrap_or(' ') {
                 self.source.next(); // eat the `e`
                 
                 let sign = match self.source.peek().copied().map(|c|c.char).unwrap_or(' ') {
@@ -336,26 +382,37 @@ impl<'i> Iterator for LinearTokenIter<'i> {
                     }
                 }
                 
-                let mut pow10: i32 = buffer.parse().unwrap();
-                if sign {pow10 *= -1;}
-                10f64.powi(pow10)
+                let exponent: i32 = match buffer.parse() {
+                    Ok(exponent) => exponent,
+                    Err(_) => return Some(self.error(index, LexErrorKind::InvalidExponent(buffer))),
+                };
+                if sign {-exponent} else {exponent}
             } else {
-                1.0
+                0
             };
             
+            let pow10 = 10f64.powi(exponent);
+            
             if decimal == 0.0 {
-                if pow10 > 0.0 {
-                    return Some((index, index, Literal::Int((sign as i64) * integer * (pow10 as i64))).into());
+                if exponent >= 0 {
+                    let value = 10i64.checked_pow(exponent as u32)
+                        .and_then(|pow10| integer.checked_mul(pow10))
+                        .map(|value| if sign < 0.0 {-value} else {value});
+                    
+                    return Some(match value {
+                        Some(value) => (index, index, Literal::Int(value)).into(),
+                        None => self.overflow(index),
+                    });
                 }
-                return Some((index, index, Literal::Dec((sign) * (integer as f64) * (pow10 as f64))).into());
+                return Some((index, index, Literal::Dec((sign) * (integer as f64) * pow10)).into());
             }
             
-            let value = (sign) * (integer as f64 + decimal) * (pow10 as f64);
+            let value = (sign) * (integer as f64 + decimal) * pow10;
             return Some((index, index, Literal::Dec(value)).into());
         }
         
         let remainder: String = self.source.clone().map(|p| p.char).collect();
-        Some((index, index+remainder.len(), TokenContent::Remainder(remainder)).into())
+        Some((index, self.input.len(), TokenContent::Remainder(remainder)).into())
     }
 }
 
@@ -385,16 +442,15 @@ fn try_lex_bareword(input: &mut PosInput, start: usize, current: char) -> (usize
     (start, end, buffer)
 }
 
-fn try_lex_string(input: &mut PosInput, start: usize, current: char, delimiter: char) -> (usize, usize, CompactString) {
+fn try_lex_string(input: &mut PosInput, start: usize, current: char, delimiter: char) -> Result<(usize, usize, CompactString), LexError> {
     let mut buffer = CompactString::new();
     let mut last = current;
-    let mut end = start;
+    let mut end = start + current.len_utf8();
     
-    #[allow(clippy::while_let_loop)]
     loop {
         let PosChar { char: peeked, idx: index , .. } = match input.peek().copied() {
             Some(i) => i,
-            None => break // TODO: Return error?
+            None => return Err(LexError::new(LexErrorKind::UnterminatedString, start..end))
         };
         
         end = index;
@@ -404,11 +460,12 @@ fn try_lex_string(input: &mut PosInput, start: usize, current: char, delimiter:
         } else {
             buffer.push(peeked);
             input.next(); // eat char
+            end += peeked.len_utf8();
             last = peeked;
         }
     }
     
-    (start, end, buffer)
+    Ok((start, end, buffer))
 }
 
 /// Try lex uuid.
@@ -1,9 +1,16 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 
-//pub mod values;
 pub mod lexer;
 pub mod parser;
+pub mod values;
+pub mod eval;
+pub mod registry;
+pub mod vm;
+pub mod optimizer;
+pub mod diagnostics;
+
+pub use parser::{parse, is_complete};
 
 // The IMPRAL language guide.
 #[cfg(feature = "guide")]
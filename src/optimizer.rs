@@ -0,0 +1,205 @@
+//! An optimisation pass over [`Block`]s, folding constant expressions ahead of evaluation.
+//! 
+//! - Invocations of pure commands (see [`Signature::pure`](crate::registry::Signature#structfield.pure))
+//!   with only literal arguments (or ranges of literals) are replaced by their result, if it can be written as literal.
+//!   Commands defined via `def` or `alias` within the block are never folded, as they replace the registered ones.
+//! - Unwrapping (`?`) values that can neither fail nor be `null` is removed.
+//! - Nodes that are no longer reachable from the entry are dropped, via [`Block::compact`].
+//! 
+//! Invocations that fail are left alone, so that they fail at evaluation just like before.
+
+use std::rc::Rc;
+
+use smartstring::alias::CompactString;
+
+use crate::eval::{Arguments, Context, RangeValue, Value};
+use crate::lexer::Literal;
+use crate::parser::{Block, BlockRef, CommandPath, Expression, FnCall};
+use crate::registry::CommandRegistry;
+
+#[cfg(test)]
+mod tests;
+
+/// Optimizes the block, resolving commands via the registry and the given imports.
+/// 
+/// The bodies of quoted code and definitions are optimized as well.
+pub fn optimize(block: &Block, registry: &mut CommandRegistry, imports: &[CommandPath]) -> Block {
+    Optimizer { registry, imports, defined: Vec::new() }.optimize(block)
+}
+
+/// The state of an optimisation.
+struct Optimizer<'a> {
+    /// The registry to resolve and invoke commands with.
+    registry: &'a mut CommandRegistry,
+    /// The namespaces and commands to resolve commands with.
+    imports: &'a [CommandPath],
+    /// The names of the commands defined within the blocks being optimized.
+    defined: Vec<CompactString>,
+}
+
+impl Optimizer<'_> {
+    /// Optimizes a block, including the bodies of its quoted code and definitions.
+    fn optimize(&mut self, block: &Block) -> Block {
+        let mut block = block.clone();
+        let enclosing = self.defined.len();
+        for (_, expr) in block.iter() {
+            if let Expression::Def(def) = expr {
+                self.defined.push(def.name.to_str().into());
+            }
+        }
+        
+        // The call of a method lacks its receiver, and thus cannot be folded on its own.
+        let mut calls = vec![false; block.len()];
+        for (_, expr) in block.iter() {
+            if let Expression::Method(_, call) = expr {
+                calls[call.index()] = true;
+            }
+        }
+        
+        // Children come before their parents, so they are always simplified first.
+        let nodes: Vec<BlockRef> = block.iter().map(|(br, _)| br).collect();
+        for br in nodes {
+            if !calls[br.index()] {
+                self.simplify(&mut block, br);
+            }
+        }
+        
+        block.compact();
+        self.defined.truncate(enclosing);
+        block
+    }
+    
+    /// Simplifies a node in place, once its children have been simplified.
+    fn simplify(&mut self, block: &mut Block, br: BlockRef) {
+        let expr = block.get(br);
+        let simplified = match expr {
+            Expression::Code(code) => {
+                let mut code = code.clone();
+                let body = self.optimize(&code.body);
+                Rc::make_mut(&mut code).body = body;
+                Expression::Code(code)
+            },
+            Expression::Def(def) => {
+                let mut def = def.clone();
+                let body = self.optimize(&def.code.body);
+                Rc::make_mut(&mut Rc::make_mut(&mut def).code).body = body;
+                Expression::Def(def)
+            },
+            expr => match self.unwrapped(block, expr) {
+                Some(target) => block.get(target).clone(),
+                None => match self.constant(block, expr) {
+                    Some(literal) => Expression::Value(literal),
+                    None => return,
+                },
+            },
+        };
+        *block.get_mut(br) = simplified;
+    }
+    
+    /// Returns the target of an unwrap that does nothing, as its target can neither fail nor be `null`.
+    fn unwrapped(&self, block: &Block, expr: &Expression) -> Option<BlockRef> {
+        let Expression::Try(target, throw) = expr else {
+            return None
+        };
+        
+        match block.get(*target) {
+            Expression::Value(Literal::Nil) if !*throw => Some(*target),
+            Expression::Value(Literal::Nil) => None,
+            Expression::Value(_) => literal(block, *target).map(|_| *target),
+            Expression::Code(_) => Some(*target),
+            // Unwrapping without throwing never fails, and unwrapping twice changes nothing.
+            Expression::Try(_, false) if !*throw => Some(*target),
+            _ => None,
+        }
+    }
+    
+    /// Returns the value of an expression as literal, if it is an invocation that can be folded.
+    fn constant(&mut self, block: &Block, expr: &Expression) -> Option<Literal> {
+        match expr {
+            Expression::FnCall(call) => self.fold_call(block, call, None),
+            Expression::Method(target, call) => {
+                let receiver = literal(block, *target)?;
+                let Expression::FnCall(call) = block.get(*call) else {
+                    return None
+                };
+                self.fold_call(block, call, Some(receiver))
+            },
+            _ => None,
+        }
+    }
+    
+    /// Invokes a pure command, if every argument is a literal.
+    fn fold_call(&mut self, block: &Block, call: &FnCall, receiver: Option<Literal>) -> Option<Literal> {
+        // Intrinsics are evaluated by the interpreter itself, no matter the registry.
+        if receiver.is_none() && matches!(call.name.segments(), [name] if is_intrinsic(name)) {
+            return None
+        }
+        
+        let name = call.name.to_str();
+        let id = self.registry.resolve(&name, self.imports)?;
+        let command = self.registry.get(id);
+        if !command.is_pure() || self.defined.iter().any(|def| *def == *name || *def == command.signature.name) {
+            return None
+        }
+        
+        let mut args = Arguments::default();
+        args.pos_args.extend(receiver.as_ref().map(Value::from));
+        for arg in &call.pos_args {
+            args.pos_args.push(argument(block, *arg)?);
+        }
+        for (key, arg) in &call.nom_args {
+            args.nom_args.insert(key.clone(), argument(block, *arg)?);
+        }
+        
+        let value = self.registry.invoke(&mut Context::default(), id, args).ok()?;
+        into_literal(value)
+    }
+    
+}
+
+/// Returns the literal value of a node, if it has one.
+fn literal(block: &Block, br: BlockRef) -> Option<Literal> {
+    match block.get(br) {
+        Expression::Value(Literal::RefRes | Literal::RefCtx | Literal::RefVar(_)) => None,
+        Expression::Value(literal) => Some(literal.clone()),
+        _ => None,
+    }
+}
+
+/// Returns the value of an argument, if it is a literal or a range of literals.
+fn argument(block: &Block, br: BlockRef) -> Option<Value> {
+    match block.get(br) {
+        Expression::Range(start, end, inclusive) => Some(Value::Range(Box::new(RangeValue {
+            start: Value::from(&literal(block, *start)?),
+            end: Value::from(&literal(block, *end)?),
+            inclusive: *inclusive,
+        }))),
+        _ => literal(block, br).map(|literal| Value::from(&literal)),
+    }
+}
+
+/// Is the command evaluated by the interpreter itself?
+fn is_intrinsic(name: &str) -> bool {
+    matches!(name, "list" | "dict" | "if-then" | "if-else" | "use" | "call")
+}
+
+/// Converts a value into a literal, if it can be written as one.
+fn into_literal(value: Value) -> Option<Literal> {
+    Some(match value {
+        Value::Nil => Literal::Nil,
+        Value::Bool(v) => Literal::Bool(v),
+        Value::Int(v) => Literal::Int(v),
+        Value::Dec(v) => Literal::Dec(v),
+        Value::Uid(v) => Literal::Uid(v),
+        Value::Str(v) => Literal::Str(v),
+        Value::Byt(v) => Literal::Byt(v),
+        Value::ObjIdx(v) => Literal::ObjIdx(v),
+        Value::ObjUid(v) => Literal::ObjUid(v),
+        Value::ObjKey(v) => Literal::ObjKey(v),
+        Value::List(_)
+        | Value::Dict(_)
+        | Value::Range(_)
+        | Value::Object(_)
+        | Value::Closure(_) => return None,
+    })
+}
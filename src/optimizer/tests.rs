@@ -0,0 +1,151 @@
+//! Tests to ensure blocks are optimized correctly.
+
+use std::cell::Cell;
+
+use super::*;
+use crate::eval::EvalError;
+use crate::registry::{BoundArgs, Kind, Signature};
+
+fn registry() -> CommandRegistry {
+    let mut registry = crate::registry::testing::registry();
+    
+    registry.register(
+        Signature::new("*")
+            .variadic("numbers", Kind::Number)
+            .pure(),
+        |_: &mut Context, args: &BoundArgs| {
+            match args.rest_as::<i64>() {
+                Ok(ints) => Ok(Value::Int(ints.into_iter().product())),
+                Err(_) => Ok(Value::Dec(args.rest_as::<f64>()?.into_iter().product())),
+            }
+        }
+    );
+    
+    registry.register(
+        Signature::new("add")
+            .positional("a", Kind::Int)
+            .positional("b", Kind::Int)
+            .pure(),
+        |_: &mut Context, args: &BoundArgs| {
+            Ok(Value::Int(args.get::<i64>("a")? + args.get::<i64>("b")?))
+        }
+    );
+    
+    registry.register(
+        Signature::new("into_radians")
+            .positional("degrees", Kind::Number)
+            .pure(),
+        |_: &mut Context, args: &BoundArgs| {
+            Ok(Value::Dec(args.get::<f64>("degrees")?.to_radians()))
+        }
+    );
+    
+    registry.register(
+        Signature::new("volume")
+            .variadic("ranges", Kind::Range)
+            .pure(),
+        |_: &mut Context, args: &BoundArgs| {
+            let mut volume = 1;
+            for range in args.rest_as::<Value>()? {
+                let Value::Range(range) = range else { unreachable!() };
+                let (Value::Int(start), Value::Int(end)) = (range.start, range.end) else {
+                    return Err(EvalError::Custom("integer ranges only".into()))
+                };
+                volume *= end - start + range.inclusive as i64;
+            }
+            Ok(Value::Int(volume))
+        }
+    );
+    
+    registry.register(
+        Signature::new("broken").pure(),
+        |_: &mut Context, _: &BoundArgs| Err(EvalError::Custom("broken".into()))
+    );
+    
+    let rolls = Cell::new(0);
+    registry.register(
+        Signature::new("roll")
+            .optional("sides", Kind::Int, 6),
+        move |_: &mut Context, args: &BoundArgs| {
+            rolls.set(rolls.get() + 1);
+            Ok(Value::Int(rolls.get() % args.get::<i64>("sides")?))
+        }
+    );
+    
+    registry
+}
+
+/// Optimizes the input, returning the tree and the number of nodes of the optimized block.
+fn optimized(input: &str) -> (String, usize) {
+    let block = crate::parse(input).expect("input should parse");
+    let block = optimize(&block, &mut registry(), &[]);
+    let debug = format!("{block:?}");
+    let tree = debug.split_once("tree: `").map(|(_, t)| t.trim_end_matches("`}").to_string()).unwrap_or(debug);
+    (tree, block.len())
+}
+
+#[test]
+fn fold_pure_commands() {
+    assert_eq!(optimized("= 2 * 3 * 10"), ("60i".into(), 1));
+    assert_eq!(optimized("* 2 PI").0, format!("{:?}", Literal::Dec(std::f64::consts::TAU)));
+    assert_eq!(optimized("+ 1 90.(+ 2)"), ("93i".into(), 1));
+    assert_eq!(optimized("into_radians 180").0, format!("{:?}", Literal::Dec(std::f64::consts::PI)));
+    assert_eq!(optimized("+ 180°").0, format!("(+ {:?})", Literal::Dec(std::f64::consts::PI)));
+    
+    // Only pure commands with literal arguments are folded.
+    assert_eq!(optimized("roll (+ 2 4)"), ("(roll 6i)".into(), 2));
+    assert_eq!(optimized("+ 1 (roll)"), ("(+ 1i (roll))".into(), 3));
+    assert_eq!(optimized("+ 1 $x"), ("(+ 1i $x)".into(), 3));
+    assert_eq!(optimized("list (+ 1 1)"), ("(list 2i)".into(), 2));
+    assert_eq!(optimized("0..3 | + $ (+ 1 1)").0, "0i3i | (+ $ 2i)");
+    assert_eq!(optimized("{|$a| + $a (* 2 3)}").0, "{|$a| (+ $a 6i)}");
+    assert_eq!(optimized("add 1 2"), ("3i".into(), 1));
+    assert_eq!(optimized("volume 0..=8 0..=8 0..=8"), ("729i".into(), 1));
+    assert_eq!(optimized("volume 0..$n").0, "(volume 0i$n)");
+    
+    // Failing invocations are left for evaluation.
+    assert_eq!(optimized("broken"), ("(broken)".into(), 1));
+    assert_eq!(optimized("+ 1 one"), ("(+ 1i one)".into(), 3));
+}
+
+#[test]
+fn simplify_unwraps() {
+    assert_eq!(optimized("(+ 1 2)?"), ("3i".into(), 1));
+    assert_eq!(optimized("(+ 1 2)?!"), ("3i".into(), 1));
+    assert_eq!(optimized("list null?"), ("(list null)".into(), 2));
+    assert_eq!(optimized("list null?!"), ("(list null??!)".into(), 3));
+    assert_eq!(optimized("list (roll)??").0, "(list (roll)?)");
+    assert_eq!(optimized("list (roll)?").0, "(list (roll)?)");
+    assert_eq!(optimized("list $x?").0, "(list $x?)");
+}
+
+#[test]
+fn purity_is_declared() {
+    let mut registry = registry();
+    registry.register(Signature::new("+").variadic("numbers", Kind::Int), |_: &mut Context, _: &BoundArgs| Ok(Value::Nil));
+    
+    let block = crate::parse("+ 1 2").expect("input should parse");
+    let optimized = optimize(&block, &mut registry, &[]);
+    assert_eq!(optimized, block);
+    assert!(!registry.get(registry.lookup("+").unwrap()).is_pure());
+    assert!(registry.get(registry.lookup("*").unwrap()).is_pure());
+}
+
+#[test]
+fn defined_commands_are_not_folded() {
+    let block = crate::parser::parse_script("def add $a $b: list $a $b; add 1 2; {|| add 3 4}").expect("input should parse");
+    let tree = format!("{:?}", optimize(&block, &mut registry(), &[]));
+    assert!(tree.contains("(add 1i 2i)"), "{tree}");
+    assert!(tree.contains("(add 3i 4i)"), "{tree}");
+}
+
+#[test]
+fn optimized_evaluation() -> Result<(), EvalError> {
+    for input in ["= 2 * 3 + 4", "roll (+ 2 4)", "(broken)?", "+ 1 2 -> $x; + $x (* 2 2)", "0..3 | + $ (+ 1 1)", "def add $a $b: list $a $b; add 1 2", "volume 0..=8 0..4"] {
+        let block = crate::parser::parse_script(input).expect("input should parse");
+        let expected = crate::eval::eval(&block, &mut registry(), &mut Context::default())?;
+        let block = optimize(&block, &mut registry(), &[]);
+        assert_eq!(crate::eval::eval(&block, &mut registry(), &mut Context::default())?, expected, "`{input}` changed");
+    }
+    Ok(())
+}
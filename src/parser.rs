@@ -26,6 +26,9 @@ pub use error::*;
 pub mod ast;
 pub use ast::*;
 
+pub mod code;
+pub use code::*;
+
 /// An active parser.
 #[derive(Default)]
 pub struct Parser {
@@ -35,6 +38,368 @@ pub struct Parser {
     pub(crate) block: Block,
     /// When the parser cannot fully parse the input, this will contain the remainder.
     pub(crate) remainder: Option<String>,
+    /// The byte-position of the end of the last consumed token.
+    pub(crate) end: usize,
+    /// Should errors be recorded and recovered from, instead of aborting the parse?
+    pub(crate) recover: bool,
+    /// The errors recorded while recovering.
+    pub(crate) errors: Vec<ParseError>,
+    /// Should infix expressions stop at `&&` and `||`, leaving them to the enclosing command?
+    pub(crate) in_argument: bool,
+}
+
+/// Options for [`Parser::parse`].
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Should the input start with a command?
+    pub start_cmd: bool,
+    /// May the input be a pipe?
+    pub start_pipe: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            start_cmd: true,
+            start_pipe: true,
+        }
+    }
+}
+
+impl Parser {
+    /// Parses the given input into an owned [`Block`].
+    /// 
+    /// This runs the whole pipeline: lexer → groupenizer → parser.
+    pub fn parse(&mut self, input: &str, options: ParseOptions) -> Result<Block, ParseError> {
+        self.recover = false;
+        let output = self.parse_tokens(input, options, false);
+        self.finish(input, output)
+    }
+    
+    /// Parses the given input as a script into an owned [`Block`], with an [`Expression::Sequence`] as entry.
+    /// 
+    /// Statements are separated by `;` or line-breaks; a line ending in `|`, `&&`, `||`, `:` or `=` continues on the next,
+    /// as does a line starting with `|`, `&&`, `||` or an arrow.
+    pub fn parse_script(&mut self, input: &str) -> Result<Block, ParseError> {
+        self.recover = false;
+        let output = self.parse_tokens(input, ParseOptions::default(), true);
+        self.finish(input, output)
+    }
+    
+    /// Takes the parsed block, located within the given input.
+    fn finish(&mut self, input: &str, output: Result<BlockRef, ParseError>) -> Result<Block, ParseError> {
+        let map = SourceMap::new(input);
+        let mut block = std::mem::take(&mut self.block);
+        let expr = output.map_err(|err| {
+            let mut err = match incomplete(input) {
+                Some(incomplete) if !is_before(&err, &incomplete, input) => incomplete,
+                _ => err,
+            };
+            err.locate(&map);
+            err
+        })?;
+        
+        block.entry = Some(expr);
+        block.locate(&map);
+        Ok(block)
+    }
+    
+    /// Parses the given input into a best-effort [`Block`], recovering from errors.
+    /// 
+    /// Every expression that fails to parse is recorded as an [`Expression::Error`],
+    /// skipping ahead to the next `;`, `|` or closing delimiter; parsing then continues.
+    /// Statements separated by `;` are recovered one by one, into an [`Expression::Sequence`].
+    /// 
+    /// Returns the block together with every error, in the order they were encountered.
+    pub fn parse_recovering(&mut self, input: &str, options: ParseOptions) -> (Block, Vec<ParseError>) {
+        self.recover = true;
+        let map = SourceMap::new(input);
+        let output = self.parse_tokens(input, options, false);
+        
+        let mut block = std::mem::take(&mut self.block);
+        let mut errors = std::mem::take(&mut self.errors);
+        self.recover = false;
+        
+        match output {
+            Ok(expr) => block.entry = Some(expr),
+            Err(err) => errors.push(err),
+        }
+        
+        for err in &mut errors {
+            err.locate(&map);
+        }
+        
+        block.locate(&map);
+        (block, errors)
+    }
+    
+    /// Runs the whole pipeline over the given input, leaving the nodes in `self.block`.
+    /// 
+    /// When recovering, a command is split into statements at every `;`,
+    /// so that an error in one statement does not swallow the following ones.
+    fn parse_tokens(&mut self, input: &str, options: ParseOptions, script: bool) -> Result<BlockRef, ParseError> {
+        use peekmore::PeekMore;
+        let mut tokens = tokenize(input).peekmore();
+        let tokens: Vec<Token> = groupenize(&mut tokens, None).collect();
+        
+        self.depth = 0;
+        self.remainder = None;
+        self.end = 0;
+        self.errors.clear();
+        
+        if !script && !self.recover {
+            return self.parse_statement(tokens, options)
+        }
+        
+        let start = tokens.first().map(|t| t.start).unwrap_or(0);
+        let mut statements = split_statements(tokens, script);
+        if !script && statements.len() <= 1 {
+            return self.parse_statement(statements.pop().unwrap_or_default(), options)
+        }
+        
+        let statements = statements.into_iter()
+            .map(|statement| self.parse_statement(statement, options))
+            .collect::<Result<ExpressionVec, ParseError>>()?;
+        
+        Ok(self.block.emplace(Expression::Sequence(statements), start..self.end))
+    }
+    
+    /// Parses a single statement, which must span all of the tokens.
+    fn parse_statement(&mut self, tokens: Vec<Token>, options: ParseOptions) -> Result<BlockRef, ParseError> {
+        use peekmore::PeekMore;
+        
+        // A statement that ends before its expression does, ends in a dangling operator.
+        let last = tokens.last().map(Token::span);
+        let dangling = |err| match (err, last) {
+            (ParseError::Empty, Some(span)) => ParseError::ExpectButEnd(span, "an expression"),
+            (err, _) => err,
+        };
+        
+        let errors = self.errors.len();
+        let tokens: Box<dyn TokenStream> = Box::new(tokens.into_iter());
+        let tokens = &mut tokens.peekmore();
+        let expr = self.recovering(tokens, |parser, tokens|
+            parse_expression(parser, tokens, options.start_cmd, options.start_pipe)
+        ).map_err(dangling)?;
+        
+        for err in &mut self.errors[errors..] {
+            *err = dangling(std::mem::replace(err, ParseError::Empty));
+        }
+        
+        if let Some(token) = tokens.next() {
+            let err = ParseError::Unexpected(token.span(), format!("trailing token {token}").into());
+            if !self.recover {
+                return Err(err)
+            }
+            
+            // Nothing can follow the entry expression; drop the rest.
+            self.errors.push(err);
+            for token in tokens {
+                self.consumed(token.end);
+            }
+        }
+        
+        Ok(expr)
+    }
+    
+    /// Runs the given parsing function over the tokens.
+    /// 
+    /// When recovering, an error is recorded and replaced by an [`Expression::Error`],
+    /// after skipping ahead to the next `;`, `|` or closing delimiter.
+    pub(crate) fn recovering(
+        &mut self,
+        tokens: &mut PeekableTokenStream,
+        parse: impl FnOnce(&mut Parser, &mut PeekableTokenStream) -> Result<BlockRef, ParseError>,
+    ) -> Result<BlockRef, ParseError> {
+        if !self.recover {
+            return parse(self, tokens)
+        }
+        
+        let depth = self.depth;
+        let start = tokens.peek().map(|t| t.start).unwrap_or(self.end);
+        let err = match parse(self, tokens) {
+            Ok(expr) => return Ok(expr),
+            Err(err) => err,
+        };
+        
+        self.depth = depth;
+        self.recover(tokens, err)?;
+        Ok(self.block.emplace(Expression::Error, self.span(start)))
+    }
+    
+    /// Records the error and skips ahead to the next `;`, `|` or closing delimiter;
+    /// unless not recovering, in which case the error is returned.
+    pub(crate) fn recover(&mut self, tokens: &mut PeekableTokenStream, err: ParseError) -> Result<(), ParseError> {
+        if !self.recover {
+            return Err(err)
+        }
+        
+        if let Some(span) = err.span() {
+            self.consumed(span.end);
+        }
+        
+        while let Some(token) = consume_if(tokens, |tc| !matches!(tc,
+            TokenContent::Symbol(Symbol::Semicolon | Symbol::Pipe)
+        ) && !matches!(tc, TokenContent::Symbol(s) if s.is_end_delimiter())) {
+            self.consumed(token.end);
+        }
+        
+        self.errors.push(err);
+        Ok(())
+    }
+    
+    /// Marks a token ending at the given byte-position as consumed, extending the current span.
+    pub(crate) fn consumed(&mut self, end: usize) {
+        self.end = self.end.max(end);
+    }
+    
+    /// Returns the span of the next token, or an empty span after the last consumed token.
+    pub(crate) fn here(&self, tokens: &mut PeekableTokenStream) -> Span {
+        tokens.peek().map(|t| t.span()).unwrap_or_else(|| (self.end..self.end).into())
+    }
+    
+    /// Returns the span from the given start to the end of the last consumed token.
+    pub(crate) fn span(&self, start: usize) -> std::ops::Range<usize> {
+        start..self.end
+    }
+    
+    /// Returns the input the lexer was unable to handle during the last parse, if any.
+    pub fn remainder(&self) -> Option<&str> {
+        self.remainder.as_deref()
+    }
+}
+
+/// Parses the given input as a command, returning an owned [`Block`].
+pub fn parse(input: &str) -> Result<Block, ParseError> {
+    Parser::default().parse(input, ParseOptions::default())
+}
+
+/// Checks if the given input is complete, i.e. does not end in the middle of an expression.
+/// 
+/// Input ending within a group or string, or after a `|`, `&&`, `||`, `:` or `=`, is incomplete;
+/// a REPL can then ask for another line, instead of reporting an error.
+/// Input that already contains an error, such as a stray `)`, is never incomplete.
+pub fn is_complete(input: &str) -> bool {
+    incomplete(input).is_none()
+}
+
+/// Splits tokens into statements, at every `;`, and at every line-break if `lines` is true.
+fn split_statements(tokens: Vec<Token>, lines: bool) -> Vec<Vec<Token>> {
+    let mut statements = Vec::new();
+    let mut statement: Vec<Token> = Vec::new();
+    
+    for token in tokens {
+        if matches!(token.content, TokenContent::Symbol(Symbol::Semicolon)) {
+            if !statement.is_empty() {
+                statements.push(std::mem::take(&mut statement));
+            }
+            continue;
+        }
+        
+        if let Some(previous) = statement.last() {
+            if lines && token.start_lc.line > previous.end_lc.line && !expects_more(previous) && !continues(&token) {
+                statements.push(std::mem::take(&mut statement));
+            }
+        }
+        
+        statement.push(token);
+    }
+    
+    if !statement.is_empty() {
+        statements.push(statement);
+    }
+    
+    statements
+}
+
+/// Checks if the token is a symbol that must be followed by an expression.
+fn expects_more(token: &Token) -> bool {
+    matches!(token.content, TokenContent::Symbol(
+        Symbol::Pipe |
+        Symbol::DoubleAmpersand |
+        Symbol::DoublePipe |
+        Symbol::DoubleDot |
+        Symbol::EqualSign
+    ))
+}
+
+/// Checks if the error lies before the point at which the input is incomplete, and not at its end;
+/// such an error is a real one, which more input would not resolve.
+fn is_before(err: &ParseError, incomplete: &ParseError, input: &str) -> bool {
+    if matches!(err, ParseError::ExpectButEnd(..)) {
+        return false
+    }
+    
+    match (err.span(), incomplete.span()) {
+        (Some(err), Some(incomplete)) => err.start < incomplete.start && err.end < input.trim_end().len(),
+        _ => false,
+    }
+}
+
+/// Checks if the token, starting a line, continues the statement of the previous line.
+fn continues(token: &Token) -> bool {
+    matches!(token.content, TokenContent::Symbol(symbol) if symbol.is_arrow() || matches!(symbol,
+        Symbol::Pipe |
+        Symbol::DoubleAmpersand |
+        Symbol::DoublePipe
+    ))
+}
+
+/// Returns a [`ParseError::Incomplete`] if the given input is incomplete.
+fn incomplete(input: &str) -> Option<ParseError> {
+    use peekmore::PeekMore;
+    let mut tokens = tokenize(input).peekmore();
+    let tokens: Vec<Token> = groupenize(&mut tokens, None).collect();
+    
+    let (last, rest) = tokens.split_last()?;
+    
+    // More input cannot resolve an earlier error.
+    if rest.iter().any(|t| matches!(t.content, TokenContent::Error(_))) {
+        return None
+    }
+    
+    let expected: Cow<'static, str> = match tokens.as_slice() {
+        // A pipe segment like `|>`, `|?` or `|?!` needs its expression.
+        [.., Token {content: TokenContent::Symbol(Symbol::Pipe), ..}, Token {content: TokenContent::Symbol(
+            symbol @ (Symbol::AngleRight | Symbol::QuestionMark | Symbol::ExclamationMark)
+        ), ..}] => format!("an expression after `|{symbol}`").into(),
+        [.., Token {content: TokenContent::Symbol(Symbol::Pipe), ..}, Token {content: TokenContent::Symbol(Symbol::QuestionMark), ..}, Token {content: TokenContent::Symbol(
+            Symbol::ExclamationMark
+        ), ..}] => "an expression after `|?!`".into(),
+        
+        [.., last @ Token {content: TokenContent::Symbol(symbol), ..}] if expects_more(last)
+            => format!("an expression after `{symbol}`").into(),
+        
+        [.., Token {content: TokenContent::Error(err), ..}] => match err.kind {
+            LexErrorKind::UnterminatedString => "a closing quote".into(),
+            LexErrorKind::UnterminatedArray => "`]`".into(),
+            LexErrorKind::UnclosedDelimiter(symbol) => format!("`{}`", symbol.get_delimiter()?).into(),
+            _ => return None,
+        },
+        
+        _ => return None,
+    };
+    
+    let span = match &last.content {
+        TokenContent::Error(err) => err.span,
+        _ => last.span(),
+    };
+    
+    Some(ParseError::Incomplete { span, expected })
+}
+
+/// Parses the given input as a script of statements, returning an owned [`Block`].
+/// 
+/// See [`Parser::parse_script`].
+pub fn parse_script(input: &str) -> Result<Block, ParseError> {
+    Parser::default().parse_script(input)
+}
+
+/// Parses the given input as a command, returning a best-effort [`Block`] and every error.
+/// 
+/// See [`Parser::parse_recovering`].
+pub fn parse_recovering(input: &str) -> (Block, Vec<ParseError>) {
+    Parser::default().parse_recovering(input, ParseOptions::default())
 }
 
 #[cfg(test)]
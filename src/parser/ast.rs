@@ -1,23 +1,35 @@
 //! The abstract-syntax-tree.
 
+use std::rc::Rc;
+
 use super::*;
 
 /// A linearized tree of expression nodes.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Block {
     /// Expression items table.
     pub(crate) items: Vec<Expression>,
     
     /// Expression spans table.
-    pub(crate) spans: Vec<std::ops::Range<usize>>,
+    pub(crate) spans: Vec<Span>,
     
     /// Entrypoint
     pub(crate) entry: Option<BlockRef>,
 }
 
 /// A block-internal reference.
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BlockRef(usize);
 
+impl BlockRef {
+    /// Returns the position of the referenced item within its block.
+    /// 
+    /// Useful for keeping side-tables alongside a [`Block`].
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
 impl Debug for BlockRef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "#{}", self.0)
@@ -29,42 +41,31 @@ impl Default for Block {
         Self {
             items: Vec::with_capacity(16),
             spans: Vec::with_capacity(16),
-            entry: None
+            entry: None,
         }
     }
 }
 
 impl Block {
     /// Emplaces an expression into the block, returning a BlockRef.
-    pub fn emplace(&mut self, expr: Expression, span: std::ops::Range<usize>) -> BlockRef {
-        if let Expression::Value(val) = &expr {
-            if let Some(r) = self.items.iter()
-                .enumerate()
-                .filter_map(|(i,v)|
-                    if let Expression::Value(v) = v {
-                        Some((i,v))
-                    } else {None}
-                )
-                .find(|(_, v)| v == &val)
-                .map(|(i,_)| i) {
-                return BlockRef(r)
-            }
-        }
-        
+    /// 
+    /// Every occurrence of a value gets its own item, so that it keeps its own span;
+    /// see [`Block::dedup`] for storing identical values only once.
+    pub fn emplace(&mut self, expr: Expression, span: impl Into<Span>) -> BlockRef {
         let blockref = self.items.len();
         self.items.push(expr);
-        self.spans.push(span);
+        self.spans.push(span.into());
         BlockRef(blockref)
     }
     
     /// Return an emplaced expression.
     pub fn get(&self, br: BlockRef) -> &Expression {
-        &self.items[br.0 as usize]
+        &self.items[br.0]
     }
     
     /// Return an emplaced expression.
     pub fn get_mut(&mut self, br: BlockRef) -> &mut Expression {
-        &mut self.items[br.0 as usize]
+        &mut self.items[br.0]
     }
     
     /// Returns the [`BlockRef`] for the last item.
@@ -78,6 +79,121 @@ impl Block {
         self.items.is_empty()
     }
     
+    /// Returns the number of items in the block.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    
+    /// Returns the entrypoint of the block, if there is one.
+    pub fn entry(&self) -> Option<BlockRef> {
+        self.entry
+    }
+    
+    /// Returns the source span of an emplaced expression.
+    pub fn span(&self, br: BlockRef) -> Span {
+        self.spans[br.0]
+    }
+    
+    /// Fills in the line and column of every span, using the given source map.
+    pub fn locate(&mut self, map: &SourceMap) {
+        for span in &mut self.spans {
+            *span = map.span(span.range());
+        }
+        
+        for item in &mut self.items {
+            match item {
+                Expression::Code(code) => Rc::make_mut(code).body.locate(map),
+                Expression::Def(def) => Rc::make_mut(&mut Rc::make_mut(def).code).body.locate(map),
+                _ => (),
+            }
+        }
+    }
+    
+    /// Returns an iterator over all items, in the order they were emplaced.
+    /// 
+    /// Since items are emplaced bottom-up, children always come before their parents.
+    pub fn iter(&self) -> impl Iterator<Item = (BlockRef, &Expression)> + '_ {
+        self.items.iter().enumerate().map(|(i, expr)| (BlockRef(i), expr))
+    }
+    
+    /// Returns an iterator over the direct children of an emplaced expression.
+    pub fn children(&self, br: BlockRef) -> Children {
+        self.get(br).children()
+    }
+    
+    /// Drops every item that is not reachable from the entry (or the last item, if there is none), renumbering the rest.
+    /// 
+    /// Parsing leaves unreachable items behind, like rewritten commands and the names of named arguments;
+    /// blocks that are kept around should thus be compacted. Spans stay with their items,
+    /// and the bodies of quoted code and definitions are compacted as well.
+    /// 
+    /// Every [`BlockRef`] into the block is invalidated.
+    pub fn compact(&mut self) {
+        self.rebuild(false);
+    }
+    
+    /// Compacts the block like [`Block::compact`], but also stores identical values and subtrees only once.
+    /// 
+    /// Merged subtrees keep the span of their first occurrence; values are merged by their bits.
+    pub fn dedup(&mut self) {
+        self.rebuild(true);
+    }
+    
+    /// Rebuilds the block from the items reachable from its root; see [`Block::compact`].
+    fn rebuild(&mut self, dedup: bool) {
+        let Some(root) = self.entry.or_else(|| self.last()) else {
+            return
+        };
+        
+        let mut rebuilt = Block::default();
+        let mut mapping = vec![None; self.len()];
+        let mut subtrees = dedup.then(FxHashMap::default);
+        let root = self.copy_into(root, &mut rebuilt, &mut mapping, subtrees.as_mut());
+        if self.entry.is_some() {
+            rebuilt.entry = Some(root);
+        }
+        *self = rebuilt;
+    }
+    
+    /// Copies an item and everything reachable from it into the other block, children first.
+    /// 
+    /// Given a table of subtrees, identical subtrees are copied only once.
+    fn copy_into(
+        &self,
+        br: BlockRef,
+        other: &mut Block,
+        mapping: &mut [Option<BlockRef>],
+        mut subtrees: Option<&mut FxHashMap<Expression, BlockRef>>
+    ) -> BlockRef {
+        if let Some(copied) = mapping[br.0] {
+            return copied
+        }
+        
+        let mut expr = self.get(br).clone();
+        let body = match &mut expr {
+            Expression::Code(code) => Some(&mut Rc::make_mut(code).body),
+            Expression::Def(def) => Some(&mut Rc::make_mut(&mut Rc::make_mut(def).code).body),
+            _ => None,
+        };
+        if let Some(body) = body {
+            body.rebuild(subtrees.is_some());
+        }
+        expr.map_children(|child| self.copy_into(child, other, mapping, subtrees.as_deref_mut()));
+        
+        let copied = match subtrees {
+            Some(subtrees) => match subtrees.get(&expr) {
+                Some(copied) => *copied,
+                None => {
+                    let copied = other.emplace(expr.clone(), self.spans[br.0]);
+                    subtrees.insert(expr, copied);
+                    copied
+                },
+            },
+            _ => other.emplace(expr, self.spans[br.0]),
+        };
+        mapping[br.0] = Some(copied);
+        copied
+    }
 }
 
 impl std::fmt::Debug for Block {
@@ -89,9 +205,10 @@ impl std::fmt::Debug for Block {
 pub mod fmt_debug;
 
 /// A expression node.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Default, PartialEq, Eq)]
 pub enum Expression {
     /// Nothing; an empty slot.
+    #[default]
     Empty,
     
     /// A reference into the block (linearized tree).
@@ -120,12 +237,21 @@ pub enum Expression {
     
     /// A pipe / generator / iterator.
     Pipe(Box<Pipe>),
-}
-
-impl Default for Expression {
-    fn default() -> Self {
-        Self::Empty
-    }
+    
+    /// An arrow, passing the value of the left expression on to the right.
+    Arrow(Arrow, BlockRef, BlockRef),
+    
+    /// A sequence of statements, evaluated in order; the result of each is passed as `$` to the next.
+    Sequence(ExpressionVec),
+    
+    /// A quoted block of code with named parameters, evaluated into a closure.
+    Code(Rc<Code>),
+    
+    /// A command definition, via `def` or `alias`.
+    Def(Rc<Definition>),
+    
+    /// An expression that failed to parse; only created when recovering from errors.
+    Error,
 }
 
 /// A (small)vec of expression nodes.
@@ -134,16 +260,233 @@ pub type ExpressionVec = SmallVec<[BlockRef; 1]>;
 /// A function call (-node) to be evaluated; created via [`crate::parser::command::parse_command`].
 #[derive(Clone, Default, PartialEq, Eq)]
 pub struct FnCall {
-    /// The name of the command.
-    pub name: CompactString,
+    /// The name of the command, which may be namespaced.
+    pub name: CommandPath,
     
     /// The positional arguments.
-    ///
+    /// 
     /// As long as there is only one positional argument, there will be no direct heap allocation.
     pub pos_args: ExpressionVec,
     
     /// The nominal/named arguments.
     pub nom_args: FxHashMap<CompactString, BlockRef>,
+    
+    /// Notes on how some of the named arguments were written, for validation.
+    pub notes: Vec<(CompactString, NomArgNote)>,
+}
+
+impl FnCall {
+    /// Was the named argument with the given name written as a flag (`+name`/`-name`)?
+    pub fn is_flag(&self, name: &str) -> bool {
+        self.notes.iter().any(|(n, note)| n == name && *note == NomArgNote::Flag)
+    }
+    
+    /// Was the named argument with the given name given more than once?
+    pub fn is_duplicate(&self, name: &str) -> bool {
+        self.notes.iter().any(|(n, note)| n == name && *note == NomArgNote::Duplicate)
+    }
+}
+
+/// A note on how a named argument of a [`FnCall`] was written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NomArgNote {
+    /// The argument was written as a flag (`+name`/`-name`).
+    Flag,
+    /// The argument was given more than once; only the last one is kept.
+    Duplicate,
+}
+
+/// The `::`-separated path of a command, like `gfx::debug::line`.
+/// 
+/// Every segment but the last is a namespace; the last segment is the name of the command.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct CommandPath {
+    /// The segments of the path; never empty once parsed.
+    segments: SmallVec<[CompactString; 1]>,
+}
+
+impl CommandPath {
+    /// The separator between the segments of a path.
+    pub const SEPARATOR: &'static str = "::";
+    
+    /// Creates a path from the given segments.
+    pub fn from_segments(segments: impl IntoIterator<Item = impl Into<CompactString>>) -> Self {
+        Self {
+            segments: segments.into_iter().map(Into::into).collect(),
+        }
+    }
+    
+    /// Returns the segments of the path.
+    pub fn segments(&self) -> &[CompactString] {
+        &self.segments
+    }
+    
+    /// Returns the name of the command, i.e. the last segment.
+    pub fn name(&self) -> &str {
+        self.segments.last().map(|s| s.as_str()).unwrap_or_default()
+    }
+    
+    /// Returns the namespaces of the command, i.e. every segment but the last.
+    pub fn namespace(&self) -> &[CompactString] {
+        self.segments.split_last().map(|(_, ns)| ns).unwrap_or_default()
+    }
+    
+    /// Is the command within a namespace?
+    pub fn is_qualified(&self) -> bool {
+        self.segments.len() > 1
+    }
+    
+    /// Appends a segment to the path.
+    pub fn push(&mut self, segment: impl Into<CompactString>) {
+        self.segments.push(segment.into());
+    }
+    
+    /// Returns the path joined by `::`; only allocates if the path is qualified.
+    pub fn to_str(&self) -> Cow<'_, str> {
+        match self.segments.as_slice() {
+            [name] => Cow::Borrowed(name.as_str()),
+            _ => Cow::Owned(self.to_string()),
+        }
+    }
+}
+
+impl From<&str> for CommandPath {
+    fn from(path: &str) -> Self {
+        Self::from_segments(path.split(Self::SEPARATOR))
+    }
+}
+
+impl From<CompactString> for CommandPath {
+    fn from(name: CompactString) -> Self {
+        if name.contains(Self::SEPARATOR) {
+            return name.as_str().into()
+        }
+        Self { segments: smallvec![name] }
+    }
+}
+
+impl From<String> for CommandPath {
+    fn from(name: String) -> Self {
+        CompactString::from(name).into()
+    }
+}
+
+impl From<&Symbol> for CommandPath {
+    fn from(symbol: &Symbol) -> Self {
+        Self { segments: smallvec![symbol.into()] }
+    }
+}
+
+impl PartialEq<str> for CommandPath {
+    fn eq(&self, other: &str) -> bool {
+        let mut other = other.split(Self::SEPARATOR);
+        self.segments.iter().all(|s| other.next() == Some(s.as_str())) && other.next().is_none()
+    }
+}
+
+impl PartialEq<&str> for CommandPath {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl std::fmt::Display for CommandPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {write!(f, "{}", Self::SEPARATOR)?}
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for CommandPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+/// A quoted block of code, written as `{|$a $b| …}`; see [`Expression::Code`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Code {
+    /// The names of the parameters, without the `$`.
+    pub params: SmallVec<[CompactString; 2]>,
+    
+    /// The body, parsed into a block of its own.
+    pub body: Block,
+}
+
+impl Code {
+    /// Returns the names of the variables the body refers to, but which are not parameters.
+    /// 
+    /// These are captured when the code is evaluated into a closure.
+    pub fn free_variables(&self) -> Vec<CompactString> {
+        let mut vars = Vec::new();
+        for (_, expr) in self.body.iter() {
+            let names = match expr {
+                Expression::Value(Literal::RefVar(name)) => vec![name.clone()],
+                Expression::Code(code) => code.free_variables(),
+                _ => continue,
+            };
+            for name in names {
+                if !self.params.contains(&name) && !vars.contains(&name) {
+                    vars.push(name);
+                }
+            }
+        }
+        vars
+    }
+}
+
+impl std::fmt::Debug for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{|")?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i > 0 {write!(f, " ")?}
+            write!(f, "${param}")?;
+        }
+        write!(f, "| ")?;
+        match self.body.entry() {
+            Some(entry) => fmt_debug::BlockDebugPrinter::from(&self.body).fmt_ref(f, entry)?,
+            None => write!(f, "_")?,
+        }
+        write!(f, "}}")
+    }
+}
+
+/// A command definition; see [`Expression::Def`].
+/// 
+/// Written as `def NAME $a $b opt=default: body`, or as `alias NAME: command _ key=_`,
+/// where every `_`-placeholder of the aliased command becomes a required parameter.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// The name of the defined command.
+    pub name: CommandPath,
+    
+    /// The names of the positional parameters, without the `$`.
+    pub positional: SmallVec<[CompactString; 2]>,
+    
+    /// The named parameters, with their default values; required if `None`.
+    pub named: Vec<(CompactString, Option<BlockRef>)>,
+    
+    /// The body, taking every parameter by name.
+    pub code: Rc<Code>,
+}
+
+impl std::fmt::Debug for Definition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "def {}", self.name)?;
+        for param in &self.positional {
+            write!(f, " ${param}")?;
+        }
+        for (param, default) in &self.named {
+            match default {
+                Some(default) => write!(f, " {param}={default:?}")?,
+                None => write!(f, " {param}=_")?,
+            }
+        }
+        write!(f, ": {:?}", self.code)
+    }
 }
 
 /// A pipe.
@@ -156,6 +499,63 @@ pub struct Pipe {
     pub stages: Vec<PipeSeg>,
 }
 
+/// The kind of an [`Expression::Arrow`], written as `LEFT ARROW RIGHT`.
+/// 
+/// Every arrow yields the value of its left expression, unless noted otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arrow {
+    /// `->`: Assigns the value to the variable on the right.
+    Assign,
+    
+    /// `+>`: Appends the value to the list in the variable on the right, creating the list if the variable is unset.
+    Append,
+    
+    /// `=>`: Binds the value to `$` and evaluates the right expression, yielding its value.
+    Bind,
+    
+    /// `~>`: Like `=>`, but the right expression is deferred until the rest of the block has been evaluated.
+    Defer,
+    
+    /// `#>`: Arranges the value as a grid (a list of rows), then binds it like `=>`.
+    Grid,
+}
+
+impl Arrow {
+    /// Returns the arrow written as the given symbol, if any.
+    pub fn from_symbol(symbol: Symbol) -> Option<Self> {
+        match symbol {
+            Symbol::ThinArrow => Some(Self::Assign),
+            Symbol::PlusArrow => Some(Self::Append),
+            Symbol::BindArrow => Some(Self::Bind),
+            Symbol::WaveArrow => Some(Self::Defer),
+            Symbol::GridArrow => Some(Self::Grid),
+            _ => None
+        }
+    }
+    
+    /// Returns the symbol the arrow is written as.
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            Self::Assign => Symbol::ThinArrow,
+            Self::Append => Symbol::PlusArrow,
+            Self::Bind => Symbol::BindArrow,
+            Self::Defer => Symbol::WaveArrow,
+            Self::Grid => Symbol::GridArrow,
+        }
+    }
+    
+    /// Does the arrow require a variable (`$NAME`) on the right?
+    pub fn targets_variable(&self) -> bool {
+        matches!(self, Self::Assign | Self::Append)
+    }
+}
+
+impl std::fmt::Display for Arrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
 /// A segment of a pipe.
 #[derive(Clone, PartialEq, Eq)]
 pub enum PipeSeg {
@@ -216,6 +616,127 @@ impl std::fmt::Debug for Expression {
                 write!(f, "{e:?}?")
             },
             Expression::Pipe(p) => write!(f, "{:?}", p),
+            Expression::Arrow(a, l, r) => write!(f, "{l:?} {a} {r:?}"),
+            Expression::Sequence(s) => {
+                for (i, statement) in s.iter().enumerate() {
+                    if i > 0 {write!(f, "; ")?}
+                    write!(f, "{statement:?}")?;
+                }
+                Ok(())
+            },
+            Expression::Code(c) => write!(f, "{c:?}"),
+            Expression::Def(d) => write!(f, "{d:?}"),
+            Expression::Error => write!(f, "<error>"),
+        }
+    }
+}
+
+impl std::hash::Hash for Expression {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Expression::Empty | Expression::Error => (),
+            Expression::Value(literal) => literal.hash(state),
+            Expression::FnCall(call) => {
+                call.name.hash(state);
+                call.pos_args.hash(state);
+                // Named arguments are unordered; their number has to suffice.
+                call.nom_args.len().hash(state);
+            },
+            Expression::Range(start, end, inclusive) => (start, end, inclusive).hash(state),
+            Expression::Field(target, name) => (target, name).hash(state),
+            Expression::Index(target, index) => (target, index).hash(state),
+            Expression::Method(target, call) => (target, call).hash(state),
+            Expression::Try(target, throw) => (target, throw).hash(state),
+            Expression::Pipe(pipe) => (pipe.source, pipe.stages.len()).hash(state),
+            Expression::Arrow(arrow, left, right) => (arrow, left, right).hash(state),
+            Expression::Sequence(statements) => statements.hash(state),
+            Expression::Code(code) => code.params.hash(state),
+            Expression::Def(def) => def.name.hash(state),
+        }
+    }
+}
+
+/// An iterator over the direct children of an [`Expression`].
+pub type Children = smallvec::IntoIter<[BlockRef; 4]>;
+
+impl Expression {
+    /// Returns an iterator over the direct children of this expression.
+    /// 
+    /// Function calls yield their positional arguments first, then their nominal arguments.
+    /// Pipes yield their source first, then the expressions of every stage in order.
+    pub fn children(&self) -> Children {
+        let mut children: SmallVec<[BlockRef; 4]> = SmallVec::new();
+        match self {
+            Expression::Empty => (),
+            Expression::Error => (),
+            Expression::Value(_) => (),
+            Expression::FnCall(call) => {
+                children.extend(call.pos_args.iter().copied());
+                children.extend(call.nom_args.values().copied());
+            },
+            Expression::Range(start, end, _) => children.extend([*start, *end]),
+            Expression::Field(target, _) => children.push(*target),
+            Expression::Index(target, index) => children.extend([*target, *index]),
+            Expression::Method(target, call) => children.extend([*target, *call]),
+            Expression::Try(target, _) => children.push(*target),
+            Expression::Arrow(_, left, right) => children.extend([*left, *right]),
+            Expression::Sequence(statements) => children.extend(statements.iter().copied()),
+            // The body of the code is a block of its own.
+            Expression::Code(_) => (),
+            Expression::Def(def) => children.extend(def.named.iter().filter_map(|(_, default)| *default)),
+            Expression::Pipe(pipe) => {
+                children.push(pipe.source);
+                for seg in &pipe.stages {
+                    match seg {
+                        PipeSeg::Collect { collector } => children.push(*collector),
+                        PipeSeg::Mapping { mapper } => children.push(*mapper),
+                        PipeSeg::Folding { initial, reducer } => children.extend([*initial, *reducer]),
+                        PipeSeg::Exclude { predicate } => children.push(*predicate),
+                        PipeSeg::Finding { predicate } => children.push(*predicate),
+                    }
+                }
+            },
+        }
+        children.into_iter()
+    }
+    
+    /// Replaces every direct child of this expression with the result of the given function.
+    /// 
+    /// Children are visited in the same order as by [`Expression::children`].
+    pub fn map_children(&mut self, mut f: impl FnMut(BlockRef) -> BlockRef) {
+        let mut map = |br: &mut BlockRef| *br = f(*br);
+        match self {
+            Expression::Empty => (),
+            Expression::Error => (),
+            Expression::Value(_) => (),
+            Expression::FnCall(call) => {
+                call.pos_args.iter_mut().for_each(&mut map);
+                call.nom_args.values_mut().for_each(&mut map);
+            },
+            Expression::Range(start, end, _) => {map(start); map(end)},
+            Expression::Field(target, _) => map(target),
+            Expression::Index(target, index) => {map(target); map(index)},
+            Expression::Method(target, call) => {map(target); map(call)},
+            Expression::Try(target, _) => map(target),
+            Expression::Arrow(_, left, right) => {map(left); map(right)},
+            Expression::Sequence(statements) => statements.iter_mut().for_each(&mut map),
+            Expression::Code(_) => (),
+            Expression::Def(def) => Rc::make_mut(def).named.iter_mut()
+                .filter_map(|(_, default)| default.as_mut())
+                .for_each(&mut map),
+            Expression::Pipe(pipe) => {
+                map(&mut pipe.source);
+                for seg in &mut pipe.stages {
+                    match seg {
+                        PipeSeg::Collect { collector } => map(collector),
+                        PipeSeg::Mapping { mapper } => map(mapper),
+                        PipeSeg::Folding { initial, reducer } => {map(initial); map(reducer)},
+                        PipeSeg::Exclude { predicate } => map(predicate),
+                        PipeSeg::Finding { predicate } => map(predicate),
+                    }
+                }
+            },
         }
     }
 }
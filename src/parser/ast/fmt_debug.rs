@@ -26,9 +26,37 @@ impl Debug for BlockDebugPrinter<'_> {
 
 impl BlockDebugPrinter<'_> {
     
-    fn fmt_ref(&self, f: &mut std::fmt::Formatter<'_>, br: BlockRef) -> std::fmt::Result {
+    pub(crate) fn fmt_ref(&self, f: &mut std::fmt::Formatter<'_>, br: BlockRef) -> std::fmt::Result {
         match self.block.get(br) {
             Expression::Empty => write!(f, "_"),
+            Expression::Code(code) => write!(f, "{code:?}"),
+            Expression::Def(def) => {
+                write!(f, "(def {}", def.name)?;
+                for param in &def.positional {
+                    write!(f, " ${param}")?;
+                }
+                for (param, default) in &def.named {
+                    write!(f, " {param}=")?;
+                    match default {
+                        Some(default) => self.fmt_ref(f, *default)?,
+                        None => write!(f, "_")?,
+                    }
+                }
+                write!(f, ": {:?})", def.code)
+            },
+            Expression::Error => write!(f, "<error>"),
+            Expression::Arrow(a, l, r) => {
+                self.fmt_ref(f, *l)?;
+                write!(f, " {a} ")?;
+                self.fmt_ref(f, *r)
+            },
+            Expression::Sequence(s) => {
+                for (i, statement) in s.iter().enumerate() {
+                    if i > 0 {write!(f, "; ")?}
+                    self.fmt_ref(f, *statement)?;
+                }
+                Ok(())
+            },
             Expression::Value(l) => std::fmt::Debug::fmt(l, f),
             Expression::FnCall(c) => {
                 write!(f, "({}", c.name)?;
@@ -102,7 +130,7 @@ impl BlockDebugPrinter<'_> {
             },
         }
     }
-    
+
 }
 
 impl<'b> From<&'b Block> for BlockDebugPrinter<'b> {
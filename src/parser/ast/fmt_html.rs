@@ -39,6 +39,8 @@ impl BlockHtmlPrinter<'_> {
         match self.block.get(br) {
             Expression::Empty => write!(f, "<span class=empty>_</span>")?,
             
+            Expression::Error => write!(f, "<span class=error>&lt;error&gt;</span>")?,
+            
             Expression::Value(lit) => self.fmt_lit(f, lit)?,
             
             Expression::Range(start, end, inclusive) => {
@@ -127,6 +129,61 @@ impl BlockHtmlPrinter<'_> {
                 write!(f, "</span>")?;
             },
             
+            Expression::Arrow(arrow, left, right) => {
+                write!(f, "<span class=arrow>")?;
+                self.fmt_ref(f, *left, false)?;
+                write!(f, " <span class=operator>{}</span> ", arrow.to_string().replace('>', "&gt;"))?;
+                self.fmt_ref(f, *right, false)?;
+                write!(f, "</span>")?;
+            },
+            
+            Expression::Code(code) => {
+                write!(f, "<span class=code>")?;
+                write!(f,  "<span class=separator>{{|</span>")?;
+                for (i, param) in code.params.iter().enumerate() {
+                    if i > 0 {write!(f, " ")?}
+                    write!(f, "<span class=param>${param}</span>")?;
+                }
+                write!(f,  "<span class=separator>|</span> ")?;
+                if !code.body.is_empty() {
+                    BlockHtmlPrinter::from(&code.body).fmt(f)?;
+                }
+                write!(f,  "<span class=separator>}}</span>")?;
+                write!(f, "</span>")?;
+            },
+            
+            Expression::Def(def) => {
+                write!(f, "<span class=def>")?;
+                write!(f,  "<span class=name>def {}</span>", def.name)?;
+                for param in &def.positional {
+                    write!(f, " <span class=param>${param}</span>")?;
+                }
+                for (param, default) in &def.named {
+                    write!(f, " <span class=key-val>")?;
+                    write!(f,  "<span class=key>{param}</span>")?;
+                    write!(f,  "<span class=separator>=</span>")?;
+                    match default {
+                        Some(default) => self.fmt_ref(f, *default, false)?,
+                        None => write!(f, "<span class=empty>_</span>")?,
+                    }
+                    write!(f, "</span>")?;
+                }
+                write!(f,  "<span class=separator>:</span> ")?;
+                if !def.code.body.is_empty() {
+                    BlockHtmlPrinter::from(&def.code.body).fmt(f)?;
+                }
+                write!(f, "</span>")?;
+            },
+            
+            Expression::Sequence(statements) => {
+                write!(f, "<span class=sequence>")?;
+                for (i, statement) in statements.iter().enumerate() {
+                    if i > 0 {write!(f, "<span class=separator>;</span> ")?}
+                    self.fmt_ref(f, *statement, true)?;
+                }
+                write!(f, "</span>")?;
+            },
+            
             Expression::Pipe(pipe) => {
                 write!(f, "<span class=pipe>")?;
                 write!(f,  "<span class=source>")?;
@@ -0,0 +1,179 @@
+//! Parsing of quoted code.
+
+use super::*;
+
+/// Checks if the tokens of a `{…}`-group start with the parameters of quoted code.
+pub fn is_code(tokens: &mut PeekableTokenStream) -> bool {
+    match_symbol(tokens, Symbol::Pipe) || match_symbol(tokens, Symbol::DoublePipe)
+}
+
+/// Parses the tokens of a `{|$a $b| …}`-group into quoted code.
+/// 
+/// The body may consist of several statements, separated by `;` or line-breaks,
+/// and is parsed into a block of its own.
+pub fn parse_code(
+    parser: &mut Parser,
+    tokens: &mut PeekableTokenStream
+) -> Result<Code, ParseError> {
+    let mut params = SmallVec::new();
+    
+    if !consume_symbol(tokens, Symbol::DoublePipe) {
+        if !consume_symbol(tokens, Symbol::Pipe) {
+            return Err(ParseError::ExpectButGot(parser.here(tokens), "parameters between `|`".into(), "something else".into()))
+        }
+        
+        loop {
+            if consume_symbol(tokens, Symbol::Pipe) {
+                break;
+            }
+            
+            match consume_if(tokens, |tc| matches!(tc, TokenContent::Literal(Literal::RefVar(_)))) {
+                Some(Token {content: TokenContent::Literal(Literal::RefVar(name)), end, ..}) => {
+                    parser.consumed(end);
+                    params.push(name);
+                },
+                _ => return Err(ParseError::ExpectButGot(parser.here(tokens), "a parameter ($NAME) or `|`".into(), "something else".into())),
+            }
+        }
+    }
+    
+    // The body goes into a block of its own, so that it can outlive the enclosing one.
+    let outer = std::mem::take(&mut parser.block);
+    let entry = parse_code_body(parser, tokens);
+    let mut body = std::mem::replace(&mut parser.block, outer);
+    
+    body.entry = Some(entry?);
+    Ok(Code { params, body })
+}
+
+/// Parses the statements of a code body.
+fn parse_code_body(
+    parser: &mut Parser,
+    tokens: &mut PeekableTokenStream
+) -> Result<BlockRef, ParseError> {
+    let start = tokens.peek().map(|t| t.start).unwrap_or(parser.end);
+    let tokens: Vec<Token> = tokens.collect();
+    
+    let mut statements = ExpressionVec::new();
+    for statement in split_statements(tokens, true) {
+        statements.push(parser.parse_statement(statement, ParseOptions::default())?);
+    }
+    
+    Ok(match statements.as_slice() {
+        [] => parser.block.emplace(Expression::Empty, start..start),
+        [statement] => *statement,
+        _ => parser.block.emplace(Expression::Sequence(statements), start..parser.end),
+    })
+}
+
+/// Parses the rest of a `def`- or `alias`-command into an [`Expression::Def`].
+/// 
+/// The `start` is the byte-position of the already consumed `def` or `alias`.
+pub fn parse_definition(
+    parser: &mut Parser,
+    tokens: &mut PeekableTokenStream,
+    start: usize,
+    alias: bool
+) -> Result<BlockRef, ParseError> {
+    let name = match consume_string_spanned(tokens) {
+        Some((name, span)) => {
+            parser.consumed(span.end);
+            parse_command_path(parser, tokens, CommandPath::from_segments([name]))?
+        },
+        None => return Err(ParseError::ExpectButGot(parser.here(tokens), "the name of the command".into(), "something else".into())),
+    };
+    
+    let mut positional = SmallVec::new();
+    let mut named = Vec::new();
+    
+    loop {
+        if let Some(colon) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::DoubleDot))) {
+            parser.consumed(colon.end);
+            break;
+        }
+        
+        if tokens.peek().is_none() {
+            return Err(ParseError::ExpectButEnd(parser.here(tokens), "`:` followed by the body"))
+        }
+        
+        if alias {
+            return Err(ParseError::ExpectButGot(parser.here(tokens), "`:` followed by the aliased command".into(), "something else".into()))
+        }
+        
+        let span = parser.here(tokens);
+        match consume_if(tokens, |tc| matches!(tc, TokenContent::Literal(Literal::RefVar(_) | Literal::Str(_)))) {
+            Some(Token {content: TokenContent::Literal(Literal::RefVar(param)), end, ..}) => {
+                parser.consumed(end);
+                if !named.is_empty() {
+                    return Err(ParseError::PosArgAfterNomArg(span))
+                }
+                positional.push(param);
+            },
+            Some(Token {content: TokenContent::Literal(Literal::Str(param)), end, ..}) => {
+                parser.consumed(end);
+                if !consume_symbol(tokens, Symbol::EqualSign) {
+                    return Err(ParseError::ExpectButGot(parser.here(tokens), "equal-sign and a default value".into(), "something else".into()))
+                }
+                let default = parse_expression(parser, tokens, false, false)?;
+                named.push((param, Some(default)));
+            },
+            _ => return Err(ParseError::ExpectButGot(span, "a parameter ($NAME or NAME=DEFAULT) or `:`".into(), "something else".into())),
+        }
+    }
+    
+    // The body goes into a block of its own, just like quoted code.
+    let outer = std::mem::take(&mut parser.block);
+    let entry = parse_code_body(parser, tokens);
+    let mut body = std::mem::replace(&mut parser.block, outer);
+    let entry = entry?;
+    
+    if alias {
+        let placeholders = alias_placeholders(&mut body, entry);
+        positional.extend(placeholders.0);
+        named.extend(placeholders.1.into_iter().map(|param| (param, None)));
+    }
+    
+    body.entry = Some(entry);
+    let params = positional.iter().cloned()
+        .chain(named.iter().map(|(param, _)| param.clone()))
+        .collect();
+    
+    let def = Definition {
+        name,
+        positional,
+        named,
+        code: Code { params, body }.into(),
+    };
+    Ok(parser.block.emplace(Expression::Def(def.into()), parser.span(start)))
+}
+
+/// Turns every `_`-placeholder among the arguments of the aliased command into a parameter.
+/// 
+/// Positional placeholders become `$_0`, `$_1`, etc., named placeholders take the name of their key.
+/// Returns the names of the positional and named parameters.
+fn alias_placeholders(body: &mut Block, entry: BlockRef) -> (Vec<CompactString>, Vec<CompactString>) {
+    let Expression::FnCall(call) = body.get(entry).clone() else {
+        return Default::default()
+    };
+    
+    let mut positional = Vec::new();
+    for arg in &call.pos_args {
+        if matches!(body.get(*arg), Expression::Empty) {
+            let param: CompactString = format!("_{}", positional.len()).into();
+            *body.get_mut(*arg) = Expression::Value(Literal::RefVar(param.clone()));
+            positional.push(param);
+        }
+    }
+    
+    let mut named: Vec<_> = call.nom_args.iter()
+        .filter(|(_, arg)| matches!(body.get(**arg), Expression::Empty))
+        .map(|(key, arg)| (key.clone(), *arg))
+        .collect();
+    named.sort();
+    
+    for (key, arg) in &named {
+        *body.get_mut(*arg) = Expression::Value(Literal::RefVar(key.clone()));
+    }
+    
+    (positional, named.into_iter().map(|(key, _)| key).collect())
+}
@@ -3,23 +3,27 @@
 use super::*;
 
 /// Try to convert the given TokenContent into a command-name...
-pub fn try_into_command_name(token: &Token) -> Result<smartstring::alias::CompactString, ParseError> {
+/// 
+/// This is only the first segment of the [`CommandPath`]; see [`parse_command_path`] for the rest.
+pub fn try_into_command_name(token: &Token) -> Result<CommandPath, ParseError> {
     match token.content.clone() {
         TokenContent::Remainder(r )
-            => Err(ParseError::Unrecognized(token.start, r)),
+            => Err(ParseError::Unrecognized(token.span(), r)),
+        TokenContent::Error(err)
+            => Err(ParseError::Lex(err)),
         
         // Every kind of symbol BUT delimiters can be a command name...
         TokenContent::Symbol(s ) if !s.is_operator()
-            => Err(ParseError::ExpectButGot("a command name".into(), format!("a '{}'", s).into())),
+            => Err(ParseError::ExpectButGot(token.span(), "a command name".into(), format!("a '{}'", s).into())),
         TokenContent::Symbol(s) => Ok((&s).into()),
         
         // Every kind of literal BUT strings cannot be a command name...
-        TokenContent::Literal(Literal::Str(s)) => Ok(s),
+        TokenContent::Literal(Literal::Str(s)) => Ok(CommandPath::from_segments([s])),
         TokenContent::Literal(l)
-            => Err(ParseError::ExpectButGot("a command name".into(), format!("a {}", l.get_type_str()).into())),
+            => Err(ParseError::ExpectButGot(token.span(), "a command name".into(), format!("a {}", l.get_type_str()).into())),
         
         TokenContent::Group(_, _)
-            => Err(ParseError::ExpectButGot("a command name".into(), "a group".to_string().into())),
+            => Err(ParseError::ExpectButGot(token.span(), "a command name".into(), "a group".to_string().into())),
     }
 }
 
@@ -31,26 +35,58 @@ pub fn parse_command(
 ) -> Result<BlockRef, ParseError> {
     let name = match tokens.next() {
         Some(n) => n,
-        None => return Err(ParseError::ExpectButEnd("a command name")),
+        None => return Err(ParseError::ExpectButEnd(parser.here(tokens), "a command name")),
     };
     
-    let name: CompactString = try_into_command_name(&name)?;
+    let start = name.start;
+    parser.consumed(name.end);
+    let name = try_into_command_name(&name)?;
+    let name = parse_command_path(parser, tokens, name)?;
     
     // At this point, we have a name.
-    parse_command_body(parser, name, tokens, terminator)
+    parse_command_body(parser, name, start, tokens, terminator)
+}
+
+/// Parses the `::`-separated segments following the first segment of a command path.
+pub fn parse_command_path(
+    parser: &mut Parser,
+    tokens: &mut PeekableTokenStream,
+    mut path: CommandPath
+) -> Result<CommandPath, ParseError> {
+    while let Some(sep) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::QuadDot))) {
+        parser.consumed(sep.end);
+        match consume_string_spanned(tokens) {
+            Some((segment, span)) => {
+                parser.consumed(span.end);
+                path.push(segment);
+            },
+            None => return Err(ParseError::ExpectButGot(parser.here(tokens), "a name after `::`".into(), "something else".into())),
+        }
+    }
+    
+    Ok(path)
 }
 
 /// Parses the stream of tokens into a command-expression.
+/// 
+/// The `start` is the byte-position of the already consumed command name.
 pub fn parse_command_body(
     parser: &mut Parser,
-    name: CompactString,
+    name: CommandPath,
+    start: usize,
     tokens: &mut PeekableTokenStream,
     terminator: Option<Symbol>
 ) -> Result<BlockRef, ParseError> {
+    // Definitions quote their body, instead of being evaluated like any other command.
+    if !name.is_qualified() && matches!(name.name(), "def" | "alias") {
+        return parse_definition(parser, tokens, start, name.name() == "alias")
+    }
+    
     let mut cmd = FnCall {
         name,
         pos_args: Default::default(),
         nom_args: Default::default(),
+        notes: Vec::new(),
     };
     
     let mut no_more_pos_args = false;
@@ -81,28 +117,36 @@ pub fn parse_command_body(
             break; // natural end of command, due to subcommand
         }
         
-        if consume_symbol(tokens, Symbol::DoubleAmpersand) {
+        if match_symbol(tokens, Symbol::DoubleAmpersand) {
+            let span = parser.span(start);
+            consume_symbol(tokens, Symbol::DoubleAmpersand);
+            
             let previous = std::mem::replace(&mut cmd, FnCall {
                 name: "if-then".into(),
                 pos_args: Default::default(),
                 nom_args: Default::default(),
+                notes: Vec::new(),
             });
             
-            cmd.pos_args.push(parser.block.emplace(previous.into(), 0..usize::MAX));
+            cmd.pos_args.push(parser.block.emplace(previous.into(), span));
             
             let subcommand = parse_command(parser, tokens, None)?;
             cmd.pos_args.push(subcommand);
             break; // natural end of command, due to IF-THEN wrapper command
         }
         
-        if consume_symbol(tokens, Symbol::DoublePipe) {
+        if match_symbol(tokens, Symbol::DoublePipe) {
+            let span = parser.span(start);
+            consume_symbol(tokens, Symbol::DoublePipe);
+            
             let previous = std::mem::replace(&mut cmd, FnCall {
                 name: "if-else".into(),
                 pos_args: Default::default(),
                 nom_args: Default::default(),
+                notes: Vec::new(),
             });
             
-            cmd.pos_args.push(parser.block.emplace(previous.into(), 0..usize::MAX));
+            cmd.pos_args.push(parser.block.emplace(previous.into(), span));
             
             let subcommand = parse_command(parser, tokens, None)?;
             cmd.pos_args.push(subcommand);
@@ -119,25 +163,29 @@ pub fn parse_command_body(
             break; // Encountered a pipe; command must end here.
         }
         
-        if consume_symbol(tokens, Symbol::Dash) {
-            if let Some(s) = consume_string(tokens) {
-                let br = parser.block.emplace(Expression::Value(Literal::Bool(false)), 0..usize::MAX);
-                cmd.nom_args.insert(s, br);
+        if let Some(flag) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::Dash))) {
+            parser.consumed(flag.end);
+            if let Some((s, span)) = consume_string_spanned(tokens) {
+                parser.consumed(span.end);
+                let br = parser.block.emplace(Expression::Value(Literal::Bool(false)), flag.start..span.end);
+                insert_nom_arg(&mut cmd, s, br, true);
                 no_more_pos_args = true;
                 continue;
             } else {
-                return Err(ParseError::ExpectButGot("a flag".into(), "something else".into()))
+                return Err(ParseError::ExpectButGot(parser.here(tokens), "a flag".into(), "something else".into()))
             }
         }
         
-        if consume_symbol(tokens, Symbol::Plus) {
-            if let Some(s) = consume_string(tokens) {
-                let br = parser.block.emplace(Expression::Value(Literal::Bool(true)), 0..usize::MAX);
-                cmd.nom_args.insert(s, br);
+        if let Some(flag) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::Plus))) {
+            parser.consumed(flag.end);
+            if let Some((s, span)) = consume_string_spanned(tokens) {
+                parser.consumed(span.end);
+                let br = parser.block.emplace(Expression::Value(Literal::Bool(true)), flag.start..span.end);
+                insert_nom_arg(&mut cmd, s, br, true);
                 no_more_pos_args = true;
                 continue;
             } else {
-                return Err(ParseError::ExpectButGot("a flag".into(), "something else".into()))
+                return Err(ParseError::ExpectButGot(parser.here(tokens), "a flag".into(), "something else".into()))
             }
         }
         
@@ -147,26 +195,32 @@ pub fn parse_command_body(
             // EXPRESSION
             
             // ...starting with what may just be a expression...
-            let expr = parse_expression(parser, tokens, false, false)?;
+            let expr = parser.recovering(tokens, |parser, tokens|
+                parse_expression(parser, tokens, false, false)
+            )?;
             
             if consume_symbol(tokens, Symbol::EqualSign) {
                 // (l)expr into key
+                let span = parser.span(token.start).into();
                 let lexpr = match parser.block.get_mut(expr) {
                     Expression::Value(val) => match val {
                         Literal::Str(s) => s.to_owned(),
-                        val => return Err(ParseError::ExpectButGot("a parameter name".into(), format!("{:?}", val).into())),
+                        val => return Err(ParseError::ExpectButGot(span, "a parameter name".into(), format!("{:?}", val).into())),
                     },
-                    expr => return Err(ParseError::ExpectButGot("a parameter name".into(), format!("{expr:?}").into())),
+                    expr => return Err(ParseError::ExpectButGot(span, "a parameter name".into(), format!("{expr:?}").into())),
                 };
                 
                 // parse value
-                let rexpr = parse_expression(parser, tokens, false, false)?;
+                let rexpr = parser.recovering(tokens, |parser, tokens|
+                    parse_expression(parser, tokens, false, false)
+                )?;
                 
-                cmd.nom_args.insert(lexpr, rexpr);
+                insert_nom_arg(&mut cmd, lexpr, rexpr, false);
                 no_more_pos_args = true;
             } else {
                 if no_more_pos_args {
-                    return Err(ParseError::PosArgAfterNomArg(token.start))
+                    parser.recover(tokens, ParseError::PosArgAfterNomArg(token.span()))?;
+                    continue;
                 }
                 
                 // Don't care, push arg, go to next iter.
@@ -177,5 +231,18 @@ pub fn parse_command_body(
         }
     }
     
-    Ok(parser.block.emplace(Expression::FnCall(cmd.into()), 0..usize::MAX))
+    Ok(parser.block.emplace(Expression::FnCall(cmd.into()), parser.span(start)))
+}
+
+/// Inserts a named argument (or flag) into the command, remembering the name if it was given before.
+fn insert_nom_arg(cmd: &mut FnCall, name: CompactString, arg: BlockRef, flag: bool) {
+    if cmd.nom_args.insert(name.clone(), arg).is_some() && !cmd.is_duplicate(&name) {
+        cmd.notes.push((name.clone(), NomArgNote::Duplicate));
+    }
+    
+    // Only the last argument of a name counts.
+    cmd.notes.retain(|(n, note)| *n != name || *note != NomArgNote::Flag);
+    if flag {
+        cmd.notes.push((name, NomArgNote::Flag));
+    }
 }
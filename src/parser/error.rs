@@ -12,25 +12,71 @@ pub enum ParseError {
     
     /// There was a character that could not be tokenized/lexed.
     #[error("Unrecognized token at {0}: {1}")]
-    Unrecognized(usize, String),
+    Unrecognized(Span, String),
     
     /// The stream of tokens ended unexpectedly.
-    #[error("Expected {0}, but reached end of stream")]
-    ExpectButEnd(&'static str),
+    #[error("Expected {1}, but reached end of stream")]
+    ExpectButEnd(Span, &'static str),
     
     /// An unexpected thing appeared.
-    #[error("Unexpected {0}")]
-    Unexpected(Cow<'static, str>),
+    #[error("Unexpected {1}")]
+    Unexpected(Span, Cow<'static, str>),
     
     /// The token stream ends with a remainder.
     #[error("Lexer failure at {0}")]
-    LexerError(usize),
+    LexerError(Span),
     
     /// Expected one thing, but got another.
-    #[error("Expected {0}, but got {1}")]
-    ExpectButGot(Cow<'static, str>, Cow<'static, str>),
+    #[error("Expected {1}, but got {2}")]
+    ExpectButGot(Span, Cow<'static, str>, Cow<'static, str>),
+    
+    /// The input could not be lexed.
+    #[error(transparent)]
+    Lex(#[from] LexError),
     
     /// Positional args cannot be written after nominal args.
     #[error("Positional args cannot be written after nominal args at {0}")]
-    PosArgAfterNomArg(usize),
+    PosArgAfterNomArg(Span),
+    
+    /// The input ended before it was complete; more input may complete it.
+    #[error("Incomplete input, expected {expected}")]
+    Incomplete {
+        /// The span of the unclosed delimiter, unterminated string or trailing operator.
+        span: Span,
+        /// What the input is still missing.
+        expected: Cow<'static, str>,
+    },
+}
+
+impl ParseError {
+    /// Returns the span of the input that caused the error, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::Empty => None,
+            ParseError::Unrecognized(span, _) => Some(*span),
+            ParseError::ExpectButEnd(span, _) => Some(*span),
+            ParseError::Unexpected(span, _) => Some(*span),
+            ParseError::LexerError(span) => Some(*span),
+            ParseError::ExpectButGot(span, _, _) => Some(*span),
+            ParseError::Lex(err) => Some(err.span),
+            ParseError::PosArgAfterNomArg(span) => Some(*span),
+            ParseError::Incomplete { span, .. } => Some(*span),
+        }
+    }
+    
+    /// Fills in the line and column of the errors span, using the given source map.
+    pub fn locate(&mut self, map: &SourceMap) {
+        let span = match self {
+            ParseError::Empty => return,
+            ParseError::Unrecognized(span, _) => span,
+            ParseError::ExpectButEnd(span, _) => span,
+            ParseError::Unexpected(span, _) => span,
+            ParseError::LexerError(span) => span,
+            ParseError::ExpectButGot(span, _, _) => span,
+            ParseError::Lex(err) => &mut err.span,
+            ParseError::PosArgAfterNomArg(span) => span,
+            ParseError::Incomplete { span, .. } => span,
+        };
+        *span = map.span(span.range());
+    }
 }
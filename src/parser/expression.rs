@@ -12,13 +12,18 @@ pub fn parse_expression(
     start_pipe: bool
 ) -> Result<BlockRef, ParseError> {
     parser.depth += 1;
+    let start = tokens.peek().map(|t| t.start).unwrap_or(parser.end);
     
-    if consume_symbol(tokens, Symbol::EqualSign) {
-        return parse_precedence(parser, tokens, Precedence::Null)
-    }
-    
-    // Try to parse an expression item...
-    let mut expr = parse_item(parser, tokens, start_cmd)?;
+    let mut expr = if consume_symbol(tokens, Symbol::EqualSign) {
+        // As an argument, `&&` and `||` belong to the command: `echo = 1 + 1 && echo 2`.
+        let in_argument = std::mem::replace(&mut parser.in_argument, !start_cmd && !start_pipe);
+        let expr = parse_precedence(parser, tokens, Precedence::Null);
+        parser.in_argument = in_argument;
+        expr?
+    } else {
+        // Try to parse an expression item...
+        parse_item(parser, tokens, start_cmd)?
+    };
     
     // Postfix operator parsing...
     loop {
@@ -26,7 +31,7 @@ pub fn parse_expression(
             break
         }
         
-        let next_expr = parse_postfix(parser, tokens, expr)?;
+        let next_expr = parse_postfix(parser, tokens, expr, start)?;
         
         // If parse_postfix returns a new expression, we continue...
         if next_expr != expr {
@@ -36,10 +41,18 @@ pub fn parse_expression(
         
         // Pipe? Pipe!
         if start_pipe && consume_symbol(tokens, Symbol::Pipe) {
-            expr = parse_pipe(parser, tokens, expr)?;
+            expr = parse_pipe(parser, tokens, expr, start)?;
             continue;
         }
         
+        // Arrow? Arrow! (but not within the arguments of a command)
+        if start_cmd || start_pipe {
+            if let Some(arrow) = parse_arrow(parser, tokens, expr, start)? {
+                expr = arrow;
+                continue;
+            }
+        }
+        
         break;
     }
     
@@ -60,22 +73,47 @@ pub fn parse_precedence(
     precedence: Precedence,
 ) -> Result<BlockRef, ParseError> {
     
-    // Try to parse an expression item...
-    let left = parse_item(parser, tokens, false)?;
-    let infix = parse_infix(parser, tokens, left, precedence)?;
+    let start = tokens.peek().map(|t| t.start).unwrap_or(parser.end);
+    
+    // Prefix operator? Parse the operand, which only binds tighter operators!
+    let left = if let Some(token) = consume_if(tokens, |tc|
+        matches!(tc, TokenContent::Symbol(s) if s.is_prefix_operator())
+    ) {
+        parser.consumed(token.end);
+        let symbol = token.get_symbol().expect("prefix operators are symbols");
+        let operand = parse_precedence(parser, tokens, Precedence::Prefix)?;
+        parser.block.emplace(Expression::FnCall(Box::new(FnCall {
+            name: (&symbol).into(),
+            pos_args: smallvec![operand],
+            nom_args: Default::default(),
+            notes: Vec::new(),
+        })), parser.span(start))
+    } else {
+        // Try to parse an expression item...
+        parse_item(parser, tokens, false)?
+    };
+    
+    let infix = parse_infix(parser, tokens, left, start, precedence)?;
     Ok(infix)
 }
+
 /// Parses precedence-based expressions from a `TokenStream`.
+/// 
+/// The `start` is the byte-position of the `left` expression.
 pub fn parse_infix(
     parser: &mut Parser,
     tokens: &mut PeekableTokenStream,
     mut left: BlockRef,
+    start: usize,
     precedence: Precedence,
 ) -> Result<BlockRef, ParseError> {
     
     while precedence < tokens.peek().map(|t| t.get_precedence()).unwrap_or(Precedence::Null) {
+        if parser.in_argument && (match_symbol(tokens, Symbol::DoubleAmpersand) || match_symbol(tokens, Symbol::DoublePipe)) {
+            break
+        }
         
-        let next_expr = parse_postfix(parser, tokens, left)?;
+        let next_expr = parse_postfix(parser, tokens, left, start)?;
         if next_expr != left {
             left = next_expr;
             continue;
@@ -87,62 +125,137 @@ pub fn parse_infix(
         };
         
         let symbol = match &token.content {
-            TokenContent::Symbol(symbol) if symbol.is_infix_operator() => symbol,
-            token  => return Err(ParseError::ExpectButGot("a symbol that is an infix operator".into(), format!("{token:?}").into())),
+            TokenContent::Symbol(symbol) if symbol.is_infix_operator() => *symbol,
+            content => return Err(ParseError::ExpectButGot(token.span(), "a symbol that is an infix operator".into(), format!("{content:?}").into())),
+        };
+        parser.consumed(token.end);
+        
+        if tokens.peek().is_none() {
+            return Err(ParseError::ExpectButEnd(token.span(), "an operand after the operator"))
+        }
+        
+        // Right-associative operators let their right operand bind the same operator again.
+        let precedence = if symbol.is_right_associative() {
+            symbol.get_precedence().lower()
+        } else {
+            symbol.get_precedence()
         };
+        let right = parse_precedence(parser, tokens, precedence)?;
         
-        let right = parse_precedence(parser, tokens, symbol.get_precedence())?;
+        let (name, left_arg) = match symbol {
+            // The logical operators short-circuit, just like between commands.
+            Symbol::DoubleAmpersand => ("if-then".into(), left),
+            Symbol::DoublePipe => ("if-else".into(), left),
+            
+            // Assignment requires a variable on the left, and is the same as an arrow to it.
+            Symbol::EqualSign => match parser.block.get(left) {
+                Expression::Value(Literal::RefVar(_)) => {
+                    left = parser.block.emplace(Expression::Arrow(Arrow::Assign, right, left), parser.span(start));
+                    continue;
+                },
+                _ => return Err(ParseError::ExpectButGot(parser.block.span(left), "a variable ($NAME)".into(), "something else".into())),
+            },
+            
+            symbol => ((&symbol).into(), left),
+        };
         
         left = parser.block.emplace(Expression::FnCall(Box::new(FnCall {
-            name: symbol.into(),
-            pos_args: smallvec![left, right],
-            nom_args: Default::default()
-        })), 0..1);
+            name,
+            pos_args: smallvec![left_arg, right],
+            nom_args: Default::default(),
+            notes: Vec::new(),
+        })), parser.span(start));
     }
     
     Ok(left)
 }
 
+/// Parses an arrow from a `TokenStream`, if there is one.
+/// 
+/// The `start` is the byte-position of the given `expr`.
+pub fn parse_arrow(
+    parser: &mut Parser,
+    tokens: &mut PeekableTokenStream,
+    expr: BlockRef,
+    start: usize,
+) -> Result<Option<BlockRef>, ParseError> {
+    let Some(token) = consume_if(tokens, |tc|
+        matches!(tc, TokenContent::Symbol(s) if s.is_arrow())
+    ) else {
+        return Ok(None)
+    };
+    
+    parser.consumed(token.end);
+    let arrow = token.get_symbol()
+        .and_then(Arrow::from_symbol)
+        .expect("arrow symbols have an arrow");
+    
+    let target = if arrow.targets_variable() {
+        match consume_if(tokens, |token| matches!(token, TokenContent::Literal(Literal::RefVar(_)))) {
+            Some(Token {content: TokenContent::Literal(var), start, end, ..}) => {
+                parser.consumed(end);
+                parser.block.emplace(Expression::Value(var), start..end)
+            },
+            _ => return Err(ParseError::ExpectButGot(parser.here(tokens), "a variable ($NAME)".into(), format!("{:?}",tokens.peek()).into()))
+        }
+    } else {
+        parse_expression(parser, tokens, true, false)?
+    };
+    
+    Ok(Some(parser.block.emplace(Expression::Arrow(arrow, expr, target), parser.span(start))))
+}
+
 /// Parses postifx expressions from a `TokenStream`.
+/// 
+/// The `start` is the byte-position of the given `expr`.
 pub fn parse_postfix(
     parser: &mut Parser,
     tokens: &mut PeekableTokenStream,
-    mut expr: BlockRef
+    mut expr: BlockRef,
+    start: usize,
 ) -> Result<BlockRef, ParseError> {
-        
+    
     // Dot? Field or Index!
-    if consume_symbol(tokens, Symbol::Dot) {
+    if let Some(dot) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::Dot))) {
+        parser.consumed(dot.end);
+        let group_end = tokens.peek().map(|t| t.end).unwrap_or(parser.end);
+        
         // Braket? Index!
         if let Some(mut tokens) = consume_group(tokens, Symbol::BraketLeft) {
-            let index = parse_expression(parser, &mut tokens, true, true)?;
+            let index = parser.recovering(&mut tokens, |parser, tokens| parse_expression(parser, tokens, true, true))?;
             
             if tokens.peek().is_some() {
-                return Err(ParseError::ExpectButGot("end of expression for index access".into(), "more tokens".into()))
+                let err = ParseError::ExpectButGot(parser.here(&mut tokens), "end of expression for index access".into(), "more tokens".into());
+                parser.recover(&mut tokens, err)?;
             }
             
-            expr = parser.block.emplace(Expression::Index(expr, index), 0..usize::MAX);
+            parser.consumed(group_end);
+            expr = parser.block.emplace(Expression::Index(expr, index), parser.span(start));
             return Ok(expr)
         }
         
         // Paren? Method!
         if let Some(mut tokens) = consume_group(tokens, Symbol::ParenLeft) {
-            let fncall = parse_command(parser, &mut tokens, None)?;
+            let fncall = parser.recovering(&mut tokens, |parser, tokens| parse_command(parser, tokens, None))?;
             
             if tokens.peek().is_some() {
-                return Err(ParseError::ExpectButGot("end of expression for method call".into(), "more tokens".into()))
+                let err = ParseError::ExpectButGot(parser.here(&mut tokens), "end of expression for method call".into(), "more tokens".into());
+                parser.recover(&mut tokens, err)?;
             }
             
-            expr = parser.block.emplace(Expression::Method(expr, fncall), 0..usize::MAX);
+            parser.consumed(group_end);
+            expr = parser.block.emplace(Expression::Method(expr, fncall), parser.span(start));
             return Ok(expr)
         }
         
-        let member = if let Some(name) = consume_string(tokens) {
+        let member = if let Some((name, span)) = consume_string_spanned(tokens) {
+            parser.consumed(span.end);
             name
         } else {
-            return Err(ParseError::ExpectButGot("member name for field access".into(), "something else".into()))
+            return Err(ParseError::ExpectButGot(parser.here(tokens), "member name for field access".into(), "something else".into()))
         };
         
-        expr = parser.block.emplace(Expression::Field(expr, member), 0..usize::MAX);
+        expr = parser.block.emplace(Expression::Field(expr, member), parser.span(start));
         return Ok(expr)
     }
     
@@ -151,53 +264,36 @@ pub fn parse_postfix(
     // Range? Parse Range!
     if consume_symbol(tokens, Symbol::Range) {
         if let Expression::Range(_, _, _) = parser.block.get_mut(expr) {
-            return Err(ParseError::ExpectButGot("a start that is not a range".into(), "a start that is a range".into()))
+            return Err(ParseError::ExpectButGot(parser.block.span(expr), "a start that is not a range".into(), "a start that is a range".into()))
         }
         
         let inclusive = consume_symbol(tokens, Symbol::EqualSign);
         let end = parse_expression(parser, tokens, false, false)?;
         
         if let Expression::Range(_, _, _) = parser.block.get_mut(end) {
-            return Err(ParseError::ExpectButGot("an end that is not a range".into(), "an end that is a range".into()))
+            return Err(ParseError::ExpectButGot(parser.block.span(end), "an end that is not a range".into(), "an end that is a range".into()))
         }
         
-        expr = parser.block.emplace(Expression::Range(expr, end, inclusive), 0..usize::MAX);
+        expr = parser.block.emplace(Expression::Range(expr, end, inclusive), parser.span(start));
         return Ok(expr)
     }
     
     // QuestionMark? Try unwrapping!
-    if consume_symbol(tokens, Symbol::QuestionMark) {
-        let throw = consume_symbol(tokens, Symbol::ExclamationMark);
-        expr = parser.block.emplace(Expression::Try(expr, throw), 0..usize::MAX);
-        return Ok(expr)
-    }
-    
-    // ThinArrow? Assign variable!
-    if consume_symbol(tokens, Symbol::ThinArrow) {
-        if let Some(Token {content: TokenContent::Literal(Literal::RefVar(var)), ..})
-            = consume_if(tokens, |token|
-                matches!(token, TokenContent::Literal(Literal::RefVar(_)))
-            )
-        {
-            let name = parser.block.emplace(Expression::Value(Literal::Str(var)), 0..usize::MAX);
-            expr = parser.block.emplace(Expression::FnCall(FnCall {
-                name: "set".into(),
-                pos_args: smallvec![
-                    name,
-                    expr
-                ],
-                nom_args: Default::default(),
-            }.into()), 0..usize::MAX);
-            return Ok(expr)
-        } else {
-            return Err(ParseError::ExpectButGot("a variable ($NAME)".into(), format!("{:?}",tokens.peek()).into()))
+    if let Some(token) = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::QuestionMark))) {
+        parser.consumed(token.end);
+        let throw = consume_if(tokens, |tc| matches!(tc, TokenContent::Symbol(Symbol::ExclamationMark)));
+        if let Some(token) = &throw {
+            parser.consumed(token.end);
         }
+        expr = parser.block.emplace(Expression::Try(expr, throw.is_some()), parser.span(start));
+        return Ok(expr)
     }
     
     // Parse arbitrary postfix operators...
-    if let Some(Token {content, ..}) = consume_if(tokens, |token|
+    if let Some(Token {content, end, ..}) = consume_if(tokens, |token|
         matches!(token, TokenContent::Symbol(s) if s.is_postop().is_some())
     ) {
+        parser.consumed(end);
         let symbol = if let TokenContent::Symbol(s)
             = content {s.is_postop().unwrap()}
             else {unreachable!()};
@@ -206,7 +302,8 @@ pub fn parse_postfix(
             name: symbol.into(),
             pos_args: smallvec![expr],
             nom_args: Default::default(),
-        }.into()), 0..usize::MAX);
+            notes: Vec::new(),
+        }.into()), parser.span(start));
         return Ok(expr)
     }
     
@@ -219,14 +316,16 @@ pub fn parse_postfix(
                 name,
                 pos_args: smallvec![expr],
                 nom_args: Default::default(),
-            }.into()), 0..usize::MAX);
+                notes: Vec::new(),
+            }.into()), parser.span(start));
             return Ok(expr)
         } else {
             expr = parser.block.emplace(Expression::FnCall(FnCall {
                 name: "relative".into(),
                 pos_args: smallvec![expr, to],
                 nom_args: Default::default(),
-            }.into()), 0..usize::MAX);
+                notes: Vec::new(),
+            }.into()), parser.span(start));
             return Ok(expr)
         }
     }
@@ -235,10 +334,13 @@ pub fn parse_postfix(
 }
 
 /// Parses a `TokenStream` into a pipe.
+/// 
+/// The `start` is the byte-position of the source `expr`.
 pub fn parse_pipe(
     parser: &mut Parser,
     tokens: &mut PeekableTokenStream,
-    expr: BlockRef
+    expr: BlockRef,
+    start: usize,
 ) -> Result<BlockRef, ParseError> {
     let mut pipe = Box::new(Pipe {
         source: expr,
@@ -258,33 +360,33 @@ pub fn parse_pipe(
             if consume_symbol(tokens, Symbol::ExclamationMark) {
                 // `|?!`
                 PipeSeg::Finding {
-                    predicate: parse_expression(parser, tokens, true, false)?,
+                    predicate: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, false))?,
                 }
             } else {
                 // `|?`
                 PipeSeg::Exclude {
-                    predicate: parse_expression(parser, tokens, true, false)?,
+                    predicate: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, false))?,
                 }
             }
         }
         // `|>`
         else if consume_symbol(tokens, Symbol::AngleRight) {
             pipe.stages.push(PipeSeg::Collect {
-                collector: parse_expression(parser, tokens, true, true)?,
+                collector: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, true))?,
             });
             break
         }
         // `|!`
         else if consume_symbol(tokens, Symbol::ExclamationMark) {
             PipeSeg::Folding {
-                initial: parse_expression(parser, tokens, true, false)?,
-                reducer: parse_expression(parser, tokens, true, false)?,
+                initial: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, false))?,
+                reducer: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, false))?,
             }
         }
         // `|`
         else {
             PipeSeg::Mapping {
-                mapper: parse_expression(parser, tokens, true, false)?,
+                mapper: parser.recovering(tokens, |parser, tokens| parse_expression(parser, tokens, true, false))?,
             }
         };
         
@@ -297,7 +399,7 @@ pub fn parse_pipe(
         break;
     }
     
-    Ok(parser.block.emplace(Expression::Pipe(pipe), 0..usize::MAX))
+    Ok(parser.block.emplace(Expression::Pipe(pipe), parser.span(start)))
 }
 
 /// Parses a `TokenStream` into an item (piece of an expression).
@@ -314,24 +416,41 @@ pub fn parse_item(
     };
     
     // Remainder? Error!
-    if let Token {
-        content: TokenContent::Remainder(remainder),
-        start, end: _
-    } = token {
-        parser.remainder = Some(remainder);
-        return Err(ParseError::LexerError(start))
+    if let TokenContent::Remainder(remainder) = &token.content {
+        parser.remainder = Some(remainder.clone());
+        return Err(ParseError::LexerError(token.span()))
     };
     
+    // Lexer failure? Error!
+    if let TokenContent::Error(err) = token.content {
+        return Err(ParseError::Lex(err))
+    }
+    
+    // Groups are consumed once their content has been parsed.
+    if !matches!(token.content, TokenContent::Group(..)) {
+        parser.consumed(token.end);
+    }
+    
     // Is it a command?
     if start_cmd {
         if let Ok(command_name) = try_into_command_name(&token) {
-            return parse_command_body(parser, command_name, tokens, None);
+            let command_name = parse_command_path(parser, tokens, command_name)?;
+            return parse_command_body(parser, command_name, token.start, tokens, None);
         }
     }
     
     // Underscore? Return an empty!
     if let TokenContent::Symbol(Symbol::Underscore) = token.content {
-        return Ok(parser.block.emplace(Expression::Empty, 0..usize::MAX))
+        return Ok(parser.block.emplace(Expression::Empty, token.start..token.end))
+    }
+    
+    // Bareword followed by `::`? A command path, passed as string!
+    if let TokenContent::Literal(Literal::Str(s)) = &token.content {
+        if match_symbol(tokens, Symbol::QuadDot) {
+            let path = parse_command_path(parser, tokens, CommandPath::from_segments([s.clone()]))?;
+            let path = Literal::Str(path.to_string().into());
+            return Ok(parser.block.emplace(Expression::Value(path), parser.span(token.start)))
+        }
     }
     
     // Literal? Pass thru directly!
@@ -340,18 +459,19 @@ pub fn parse_item(
     }
     
     // A group? Parse a subset!
+    let span = token.span();
     if let TokenContent::Group(kind, subtokens) = token.content {
         let subtokens = subtokens.into_iter();
         let subtokens: Box<dyn TokenStream> = Box::new(subtokens);
         let mut subtokens = subtokens.peekmore();
         
-        return Ok(match kind {
-            Symbol::ParenLeft => parse_expression(
+        let expr = match kind {
+            Symbol::ParenLeft => parser.recovering(&mut subtokens, |parser, tokens| parse_expression(
                 parser,
-                &mut subtokens,
+                tokens,
                 true,
                 true
-            )?,
+            ))?,
             
             Symbol::BraketLeft => {
                 let list = parse_list(
@@ -365,6 +485,11 @@ pub fn parse_item(
                 parser.block.emplace(list, token.start..token.end)
             },
             
+            Symbol::CurlyLeft if is_code(&mut subtokens) => {
+                let code = parse_code(parser, &mut subtokens)?;
+                parser.block.emplace(Expression::Code(code.into()), token.start..token.end)
+            },
+            
             Symbol::CurlyLeft => {
                 let dict = parse_map(
                     parser,
@@ -372,15 +497,19 @@ pub fn parse_item(
                 ).map(|d| Expression::FnCall(Box::new(FnCall {
                     name: "dict".into(),
                     nom_args: d,
+                    notes: Vec::new(),
                     ..Default::default()
                 })))?;
                 parser.block.emplace(dict, token.start..token.end)
             },
             
-            _ => unreachable!("encountered a token-group of unknown kind")
-        })
+            _ => return Err(ParseError::Unexpected(span, format!("token-group of unknown kind: {kind}").into()))
+        };
+        
+        parser.consumed(token.end);
+        return Ok(expr)
     }
     
-    Err(ParseError::Unexpected(format!("token content: {:?}", token.content).into()))
+    Err(ParseError::Unexpected(span, format!("token content: {:?}", token.content).into()))
 }
 
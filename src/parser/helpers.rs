@@ -45,9 +45,17 @@ pub fn consume_string(
     None
 }
 
+/// Consume string, returning its span...
+pub fn consume_string_spanned(
+    tokens: &mut PeekableTokenStream
+) -> Option<(CompactString, std::ops::Range<usize>)> {
+    let span = tokens.peek().map(|t| t.start..t.end)?;
+    consume_string(tokens).map(|str| (str, span))
+}
+
 /// Consume group...
-pub fn consume_group<'ipit, 'rpit>(
-    tokens: &'ipit mut PeekableTokenStream,
+pub fn consume_group<'rpit>(
+    tokens: &mut PeekableTokenStream,
     symbol: Symbol,
 ) -> Option<PeekableTokenStream<'rpit>> {
     if let Some(Token {
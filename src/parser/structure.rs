@@ -22,7 +22,9 @@ pub fn parse_list(
             continue;
         }
         
-        let expr = parse_expression(parser, tokens, false, true)?;
+        let expr = parser.recovering(tokens, |parser, tokens|
+            parse_expression(parser, tokens, false, true)
+        )?;
         list.push(expr);
     }
     
@@ -49,16 +51,30 @@ pub fn parse_map(
             continue;
         }
         
-        if let Some(key) = consume_string(tokens) {
-            if ! consume_symbol(tokens, Symbol::EqualSign) {
-                return Err(ParseError::ExpectButGot("equal-sign".into(), "something else".into()));
+        let key = match consume_string(tokens) {
+            Some(key) => key,
+            None => {
+                // The offending token is dropped, so that recovering always makes progress.
+                let span = parser.here(tokens);
+                if let Some(token) = tokens.next() {
+                    parser.consumed(token.end);
+                }
+                parser.recover(tokens, ParseError::ExpectButGot(span, "a key".into(), "something else".into()))?;
+                continue;
             }
-            // else: everything checks out, continue on...
-            
-            let expr = parse_expression(parser, tokens, false, true)?;
-            map.insert(key, expr);
+        };
+        
+        if ! consume_symbol(tokens, Symbol::EqualSign) {
+            let err = ParseError::ExpectButGot(parser.here(tokens), "equal-sign".into(), "something else".into());
+            parser.recover(tokens, err)?;
             continue;
         }
+        // else: everything checks out, continue on...
+        
+        let expr = parser.recovering(tokens, |parser, tokens|
+            parse_expression(parser, tokens, false, true)
+        )?;
+        map.insert(key, expr);
     }
     
     Ok(map)
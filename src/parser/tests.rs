@@ -19,9 +19,9 @@ fn sizes() {
     eprintln!("- SizeOf AST.I = {}", size_of::<FnCall>());
     eprintln!("- SizeOf AST.P = {}", size_of::<Pipe>());
     eprintln!("SizeOf [AST;1] = {}", size_of::<ExpressionVec>());
-    //eprintln!("SizeOf EXE.V = {}", size_of::<crate::values::ValContainer>());
+    eprintln!("SizeOf EXE.V = {}", size_of::<crate::values::ValContainer>());
     
-    //assert!(size_of::<crate::values::ValContainer>() == 8, "The size of a ValContainer-struct should be exactly 8 bytes.");
+    assert!(size_of::<crate::values::ValContainer>() == 8, "The size of a ValContainer-struct should be exactly 8 bytes.");
     assert!(dbg!(size_of::<FnCall>() <= 128), "The size of an FnCall-struct should be below 128 bytes.");
 }
 
@@ -235,7 +235,52 @@ fn posarg_after_nomarg() {
     chk("test 1 a=2 3 b=4").expect("positional arguments cannot be written after nominal arguments");
 }
 
-fn chk<'input>(input: &'input str) -> Result<Block, ParseError> {
+#[test]
+fn parse_lex_errors() {
+    for input in ["@", "print @", "print 99999999999999999999", "print [1 1e]", "print foo)", "print (foo]"] {
+        assert!(matches!(crate::parse(input), Err(ParseError::Lex(_))), "parsing {input:?} should fail");
+    }
+}
+
+#[test]
+fn parse_api() -> Result<(), ParseError> {
+    let block = crate::parse("print 1 2 foo=3")?;
+    let entry = block.entry.expect("a parsed block should have an entrypoint");
+    assert!(matches!(block.get(entry), Expression::FnCall(call) if call.name == "print"));
+    
+    let block = Parser::default().parse("1 + 2", ParseOptions {
+        start_cmd: false,
+        start_pipe: false,
+    });
+    assert!(block.is_err(), "trailing tokens should not be silently dropped");
+    
+    let block = Parser::default().parse("= 1 + 2", ParseOptions::default())?;
+    assert!(block.entry.is_some());
+    Ok(())
+}
+
+#[test]
+fn traverse_block() -> Result<(), ParseError> {
+    let block = crate::parse("e |? < $.health 10 | heal $ 10")?;
+    let entry = block.entry().unwrap();
+    
+    // Every child must have been emplaced before its parent.
+    for (br, expr) in block.iter() {
+        for child in expr.children() {
+            assert!(child.index() < br.index());
+        }
+    }
+    
+    // The pipe has a source and two stages.
+    assert_eq!(block.children(entry).count(), 3);
+    assert_eq!(block.iter().count(), block.len());
+    
+    let span = block.span(entry);
+    assert!(span.start <= span.end);
+    Ok(())
+}
+
+fn chk(input: &str) -> Result<Block, ParseError> {
     use peekmore::PeekMore;
     let mut stream = tokenize(input).peekmore();
     let mut stream = groupenize(&mut stream, None).peekmore();
@@ -280,3 +325,363 @@ fn chks(input: impl Iterator<Item=&'static str>) -> Result<(), ParseError> {
     }
     Ok(())
 }
+
+#[test]
+fn parse_spans() -> Result<(), ParseError> {
+    fn spans(input: &str) -> Result<Vec<&str>, ParseError> {
+        let block = crate::parse(input)?;
+        Ok(block.iter()
+            .filter(|(_, expr)| !matches!(expr, Expression::Value(_)))
+            .map(|(br, _)| &input[block.span(br).range()])
+            .collect())
+    }
+    
+    assert_eq!(spans("foo (baz 2).x.[0] 1..=3 bar=1")?, [
+        "baz 2", "(baz 2).x", "(baz 2).x.[0]", "1..=3", "foo (baz 2).x.[0] 1..=3 bar=1"
+    ]);
+    assert_eq!(spans("echo 1 && echo 2 || fail")?, [
+        "echo 1", "echo 2", "fail", "echo 2 || fail", "echo 1 && echo 2 || fail"
+    ]);
+    assert_eq!(spans("echo $v?! 5~feet [1 2].(len) {a=1}")?, [
+        "$v?!", "5~feet", "[1 2]", "len", "[1 2].(len)", "{a=1}", "echo $v?! 5~feet [1 2].(len) {a=1}"
+    ]);
+    assert_eq!(spans("list 0 1 |? is_odd $ |> list")?, [
+        "list 0 1", "is_odd $", "list", "list 0 1 |? is_odd $ |> list"
+    ]);
+    assert_eq!(spans("= 1 + 2 * 3")?, ["2 * 3", "1 + 2 * 3"]);
+    assert_eq!(spans("echo 2 -> $x")?, ["echo 2", "echo 2 -> $x"]);
+    
+    let block = crate::parse("echo\n  (fail 1)")?;
+    let span = block.span(block.children(block.entry().unwrap()).next().unwrap());
+    assert_eq!((span.start_lc.line, span.start_lc.col, span.end_lc.line, span.end_lc.col), (2, 4, 2, 10));
+    
+    let Err(ParseError::Lex(err)) = crate::parse("echo\n @") else { panic!("lexing should fail") };
+    assert_eq!(err.span.start_lc, LineCol { line: 2, col: 2 });
+    
+    let input = "heal +silent -loud";
+    let block = crate::parse(input)?;
+    let Some(Expression::FnCall(call)) = block.entry().map(|br| block.get(br)) else { panic!("expected a command") };
+    assert_eq!(&input[block.span(call.nom_args["silent"]).range()], "+silent");
+    assert_eq!(&input[block.span(call.nom_args["loud"]).range()], "-loud");
+    
+    let input = "heal +silent +loud";
+    let block = crate::parse(input)?;
+    let Some(Expression::FnCall(call)) = block.entry().map(|br| block.get(br)) else { panic!("expected a command") };
+    assert_eq!(&input[block.span(call.nom_args["silent"]).range()], "+silent");
+    assert_eq!(&input[block.span(call.nom_args["loud"]).range()], "+loud");
+    
+    let input = "echo 1 1";
+    let block = crate::parse(input)?;
+    let Some(Expression::FnCall(call)) = block.entry().map(|br| block.get(br)) else { panic!("expected a command") };
+    assert_eq!(block.span(call.pos_args[0]).range(), 5..6);
+    assert_eq!(block.span(call.pos_args[1]).range(), 7..8);
+    Ok(())
+}
+
+#[test]
+fn parse_recovering() {
+    fn recover(input: &str) -> (String, Vec<String>) {
+        let (block, errors) = crate::parser::parse_recovering(input);
+        let debug = format!("{block:?}");
+        let tree = debug.split_once("tree: `").map(|(_, t)| t.trim_end_matches("`}").to_string()).unwrap_or(debug);
+        (tree, errors.iter().map(|e| e.to_string()).collect())
+    }
+    
+    assert_eq!(recover("foo 1 bar=2"), ("(foo 1i bar=2i)".into(), vec![]));
+    
+    let (tree, errors) = recover("foo x=1 2 3");
+    assert_eq!(tree, "(foo x=1i)");
+    assert_eq!(errors, ["Positional args cannot be written after nominal args at 1:9"]);
+    
+    let (tree, errors) = recover("foo [1 x. 2] {a=1 b c=2} 'bar");
+    assert_eq!(tree, "(foo (list 1i <error>) (dict a=1i) <error>)");
+    assert_eq!(errors, [
+        "Expected member name for field access, but got something else",
+        "Expected equal-sign, but got something else",
+        "Unterminated string at 1:26",
+    ]);
+    
+    let (tree, errors) = recover("foo (bar 1=2) baz | map $. | len");
+    assert_eq!(tree, "(foo <error> baz) | (map <error>) | (len)");
+    assert_eq!(errors.len(), 2);
+    
+    // A comparison does not end the recovery, as angle brackets do not delimit.
+    let (tree, errors) = recover("foo x=1 2 > 3 bar; baz");
+    assert_eq!(tree, "(foo x=1i); (baz)");
+    assert_eq!(errors, ["Positional args cannot be written after nominal args at 1:9"]);
+    
+    // Every statement is recovered on its own.
+    let (tree, errors) = recover("foo x=1 2; bar @ ; baz");
+    assert_eq!(tree, "(foo x=1i); (bar <error>); (baz)");
+    assert_eq!(errors, [
+        "Positional args cannot be written after nominal args at 1:9",
+        "Invalid object reference at 1:16",
+    ]);
+    
+    let (tree, errors) = recover("foo (bar 1=2); baz (qux 3=4)");
+    assert_eq!(tree, "(foo <error>); (baz <error>)");
+    assert_eq!(errors.len(), 2);
+    
+    // Dangling operators are reported at their span.
+    for (input, span) in [("foo |", 4..5), ("foo x=", 5..6), ("foo |?", 5..6)] {
+        let (_, errors) = crate::parser::parse_recovering(input);
+        assert!(matches!(&errors[..], [ParseError::ExpectButEnd(s, _)] if s.range() == span), "{input}: {errors:?}");
+    }
+    
+    let (block, errors) = crate::parser::parse_recovering("foo\n  (bar -)");
+    assert!(block.entry.is_some());
+    let span = errors[0].span().expect("the error should have a span");
+    assert_eq!(span.start_lc, LineCol { line: 2, col: 9 });
+    
+    let (block, errors) = crate::parser::parse_recovering("");
+    assert!(matches!(errors[..], [ParseError::Empty]));
+    assert!(matches!(block.get(block.entry.unwrap()), Expression::Error));
+}
+
+#[test]
+fn parse_incomplete() {
+    for input in ["spawn player at=[0 0", "print \"hello", "print (foo", "foo |", "foo &&", "foo ||", "foo:", "foo x=", "foo |>", "foo |? ", "foo |?!", "foo {a=[1"] {
+        assert!(!crate::parser::is_complete(input), "{input:?} should be incomplete");
+        assert!(matches!(crate::parse(input), Err(ParseError::Incomplete {..})), "parsing {input:?} should be incomplete");
+    }
+    
+    for input in ["", "foo", "foo | bar", "foo x=1", "print (foo]", "print foo)", "[foo (bar] baz", "= 1 <2", "= $x <10", "print 99999999999999999999", "echo 1 ] (2", "echo ) [1", "echo (1 ]) [2"] {
+        assert!(crate::parser::is_complete(input), "{input:?} should be complete");
+        assert!(!matches!(crate::parse(input), Err(ParseError::Incomplete {..})), "parsing {input:?} should not be incomplete");
+    }
+    
+    // A real error before the open group is reported as such.
+    assert!(matches!(crate::parse("foo a=1 2 (3"), Err(ParseError::PosArgAfterNomArg(_))));
+    
+    let Err(ParseError::Incomplete { span, expected }) = crate::parse("foo\n(bar [1 2") else { panic!("parsing should be incomplete") };
+    assert_eq!(expected, "`)`");
+    assert_eq!(span.range(), 4..5);
+    assert_eq!(span.start_lc, LineCol { line: 2, col: 1 });
+}
+
+#[test]
+fn parse_scripts() -> Result<(), ParseError> {
+    fn statements(input: &str) -> Result<Vec<&str>, ParseError> {
+        let block = crate::parser::parse_script(input)?;
+        let entry = block.entry().expect("a parsed script should have an entrypoint");
+        let Expression::Sequence(statements) = block.get(entry) else { panic!("expected a sequence") };
+        Ok(statements.iter().map(|s| &input[block.span(*s).range()]).collect())
+    }
+    
+    assert_eq!(statements("a; b; c")?, ["a", "b", "c"]);
+    assert_eq!(statements("a 1 2;\nb\n\n  c (d\n  e);;")?, ["a 1 2", "b", "c (d\n  e)"]);
+    assert_eq!(statements("a |\n  b &&\n  c\nd x=\n  1")?, ["a |\n  b &&\n  c", "d x=\n  1"]);
+    assert_eq!(statements("echo a\n| echo $\nb\n  && c\n  -> $x")?, ["echo a\n| echo $", "b\n  && c\n  -> $x"]);
+    assert!(statements("\n;\n")?.is_empty());
+    
+    let block = crate::parser::parse_script("a\nb")?;
+    let span = block.span(block.entry().unwrap());
+    assert_eq!((span.range(), span.end_lc.line), (0..3, 2));
+    
+    assert!(crate::parser::parse_script("a\n)").is_err());
+    crate::parser::parse_script(SRC_PIPES)?;
+    crate::parser::parse_script(SRC_IFS)?;
+    Ok(())
+}
+
+/// Parses the input, returning only the expression tree of the block.
+fn tree(input: &str) -> Result<String, ParseError> {
+    let debug = format!("{:?}", crate::parse(input)?);
+    Ok(debug.split_once("tree: `").map(|(_, t)| t.trim_end_matches("`}").to_string()).unwrap_or(debug))
+}
+
+#[test]
+fn parse_infix_operators() -> Result<(), ParseError> {
+    assert_eq!(tree("= 1 + 2 * 3")?, "(+ 1i (* 2i 3i))");
+    assert_eq!(tree("= 1 - 2 - 3")?, "(- (- 1i 2i) 3i)");
+    assert_eq!(tree("= 2 ** 3 ** 2")?, "(** 2i (** 3i 2i))");
+    assert_eq!(tree("= - 2 ** 2")?, "(- (** 2i 2i))");
+    assert_eq!(tree("= - $x * 2")?, "(* (- $x) 2i)");
+    assert_eq!(tree("= !$a && $b")?, "(if-then (! $a) $b)");
+    assert_eq!(tree("= $.health < 10")?, "(< $.health 10i)");
+    assert_eq!(tree("= $x <10")?, "(< $x 10i)");
+    assert_eq!(tree("= 1 <2 > 0")?, "(> (< 1i 2i) 0i)");
+    assert_eq!(tree("= 1 + 2 >= 3 == 4 <> 5")?, "(== (>= (+ 1i 2i) 3i) (<> 4i 5i))");
+    assert_eq!(tree("= $a || $b && $c != 1")?, "(if-else $a (if-then $b (!= $c 1i)))");
+    assert_eq!(tree("= $a = $b = 1 + 1")?, "(+ 1i 1i) -> $b -> $a");
+    assert_eq!(tree("echo = 1 + 1 && echo 2")?, "(if-then (echo (+ 1i 1i)) (echo 2i))");
+    assert_eq!(tree("echo = $a || echo $b")?, "(if-else (echo $a) (echo $b))");
+    assert_eq!(tree("echo (= $a || $b)")?, "(echo (if-else $a $b))");
+    assert!(crate::parse("= 1 = 2").is_err());
+    
+    // An operator without a right operand is reported at its own span.
+    for (input, span) in [("= 1 <", 4..5), ("echo (= $a ||) 1", 11..13)] {
+        let err = crate::parse(input).expect_err(input);
+        assert!(matches!(&err, ParseError::ExpectButEnd(s, _) if s.range() == span), "{input}: {err:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_arrows() -> Result<(), ParseError> {
+    assert_eq!(tree("foo -> $x")?, "(foo) -> $x");
+    assert_eq!(tree("foo 1 +> $x")?, "(foo 1i) +> $x");
+    assert_eq!(tree("foo => bar $")?, "(foo) => (bar $)");
+    assert_eq!(tree("foo ~> bar")?, "(foo) ~> (bar)");
+    assert_eq!(tree("foo #> print $")?, "(foo) #> (print $)");
+    assert_eq!(tree("foo -> $x => bar")?, "(foo) -> $x => (bar)");
+    assert!(crate::parse("foo -> bar").is_err());
+    assert!(crate::parse("foo +> 1").is_err());
+    assert!(crate::parse("foo =>").is_err());
+    Ok(())
+}
+
+#[test]
+fn parse_command_paths() -> Result<(), ParseError> {
+    assert_eq!(tree("world::spawn zombie")?, "(world::spawn zombie)");
+    assert_eq!(tree("gfx::debug::line 1 2 color=red")?, "(gfx::debug::line 1i 2i color=red)");
+    assert_eq!(tree("use gfx::debug")?, "(use \"gfx::debug\")");
+    assert_eq!(tree("echo (world::time)")?, "(echo (world::time))");
+    
+    let block = crate::parse("gfx::debug::line")?;
+    let Some(Expression::FnCall(call)) = block.entry().map(|e| block.get(e)) else {
+        panic!("expected a command")
+    };
+    assert_eq!(call.name.namespace(), ["gfx", "debug"]);
+    assert_eq!(call.name.name(), "line");
+    assert_eq!(call.name, "gfx::debug::line");
+    
+    assert!(crate::parse("world::").is_err());
+    assert!(crate::parse("world::1").is_err());
+    Ok(())
+}
+
+#[test]
+fn parse_code() -> Result<(), ParseError> {
+    assert_eq!(tree("echo {|$a $b| + $a $b}")?, "(echo {|$a $b| (+ $a $b)})");
+    assert_eq!(tree("echo {|| foo; bar $}")?, "(echo {|| (foo); (bar $)})");
+    assert_eq!(tree("echo {| | }")?, "(echo {|| _})");
+    assert_eq!(tree("on-death {|$who| echo {|| say $who}}")?, "(on-death {|$who| (echo {|| (say $who)})})");
+    assert_eq!(tree("echo {a=1}")?, "(echo (dict a=1i))");
+    
+    let block = crate::parse("echo {|$a| + $a $b\n  * 2 $c}")?;
+    let Some(Expression::FnCall(call)) = block.entry().map(|e| block.get(e)) else {
+        panic!("expected a command")
+    };
+    let Expression::Code(code) = block.get(call.pos_args[0]) else {
+        panic!("expected quoted code")
+    };
+    assert_eq!(code.free_variables(), ["b", "c"]);
+    let span = code.body.span(code.body.entry().unwrap());
+    assert_eq!((span.start, span.start_lc.line, span.end_lc.line), (11, 1, 2));
+    
+    assert!(crate::parse("echo {|a| foo}").is_err());
+    assert!(crate::parse("echo {|$a foo}").is_err());
+    Ok(())
+}
+
+#[test]
+fn parse_definitions() -> Result<(), ParseError> {
+    assert_eq!(tree("def greet $who loud=false: say $who +loud")?, "(def greet $who loud=false: {|$who $loud| (say $who loud=true)})");
+    assert_eq!(tree("def world::reset: clear; spawn")?, "(def world::reset: {|| (clear); (spawn)})");
+    assert_eq!(tree("alias foo: bar")?, "(def foo: {|| (bar)})");
+    assert_eq!(tree("alias foo: bar _ 1 _ baz=_")?, "(def foo $_0 $_1 baz=_: {|$_0 $_1 $baz| (bar $_0 1i $_1 baz=$baz)})");
+    
+    assert!(crate::parse("def: foo").is_err());
+    assert!(crate::parse("def foo $a").is_err());
+    assert!(crate::parse("def foo a=1 $b: bar").is_err());
+    assert!(crate::parse("def foo a: bar").is_err());
+    assert!(crate::parse("alias foo $a: bar").is_err());
+    Ok(())
+}
+
+#[test]
+fn compact_blocks() -> Result<(), ParseError> {
+    fn reachable(block: &Block, br: BlockRef, seen: &mut Vec<BlockRef>) {
+        if !seen.contains(&br) {
+            seen.push(br);
+            block.children(br).for_each(|child| reachable(block, child, seen));
+        }
+    }
+    
+    for input in ["echo 1 && echo 2 || fail", "heal bob amount=5 +silent", "foo (baz 2).x.[0] 1..=3", "echo {|$a| + $a amount=1}"] {
+        let parsed = crate::parse(input)?;
+        let mut block = parsed.clone();
+        block.compact();
+        
+        let mut seen = Vec::new();
+        reachable(&block, block.entry().unwrap(), &mut seen);
+        assert_eq!(seen.len(), block.len(), "`{input}` keeps unreachable items");
+        assert!(block.len() <= parsed.len());
+        assert_eq!(format!("{block:?}").split_once("tree").map(|(_, t)| t), format!("{parsed:?}").split_once("tree").map(|(_, t)| t));
+        assert_eq!(block.span(block.entry().unwrap()), parsed.span(parsed.entry().unwrap()));
+        
+        for (br, expr) in block.iter() {
+            for child in expr.children() {
+                assert!(child.index() < br.index());
+            }
+        }
+    }
+    
+    let mut block = crate::parse("heal bob amount=5")?;
+    let before = block.len();
+    block.compact();
+    assert!(block.len() < before, "the name of the named argument should be dropped");
+    assert_eq!(format!("{block:?}"), "Block {vcount: 2, ecount: 1, entry: Some(#2), tree: `(heal bob amount=5i)`}");
+    
+    let input = "echo 1 && echo 2";
+    let mut block = crate::parse(input)?;
+    block.compact();
+    let spans: Vec<_> = block.iter().map(|(br, _)| &input[block.span(br).range()]).collect();
+    assert_eq!(spans, ["1", "echo 1", "2", "echo 2", "echo 1 && echo 2"]);
+    
+    let mut block = crate::parse("echo {|| heal bob amount=5}")?;
+    block.compact();
+    let Expression::Code(code) = block.iter().find(|(_, e)| matches!(e, Expression::Code(_))).unwrap().1 else { unreachable!() };
+    assert_eq!(code.body.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn intern_values() -> Result<(), ParseError> {
+    fn values(block: &Block) -> usize {
+        block.iter().filter(|(_, e)| matches!(e, Expression::Value(_))).count()
+    }
+    
+    // Every occurrence of a value keeps its own item, unless the block is deduplicated.
+    let input = "echo 1 2.5 1 2.5 NaN NaN a \"a\" $a";
+    let mut block = crate::parse(input)?;
+    block.compact();
+    assert_eq!(values(&block), 9);
+    assert_eq!(block.span(block.children(block.entry().unwrap()).nth(2).unwrap()).start, 11);
+    block.dedup();
+    assert_eq!(values(&block), 5);
+    
+    // Decimals are interned by their bits.
+    let mut block = Block::default();
+    let args = [0.0, -0.0, 0.0].map(|v| block.emplace(Expression::Value(Literal::Dec(v)), 0..0));
+    let call = FnCall { name: "echo".into(), pos_args: args.into_iter().collect(), ..Default::default() };
+    block.entry = Some(block.emplace(Expression::FnCall(call.into()), 0..0));
+    block.dedup();
+    assert_eq!(values(&block), 2);
+    Ok(())
+}
+
+#[test]
+fn dedup_blocks() -> Result<(), ParseError> {
+    let input = "echo (+ 1 2) (+ 1 2).x {a=(+ 1 2)}";
+    let mut compacted = crate::parse(input)?;
+    compacted.compact();
+    let mut deduped = crate::parse(input)?;
+    deduped.dedup();
+    
+    assert_eq!(format!("{compacted:?}").split_once("tree").map(|(_, t)| t), format!("{deduped:?}").split_once("tree").map(|(_, t)| t));
+    // Two of the three `+ 1 2` subtrees, and four of their six values.
+    assert_eq!(compacted.len() - deduped.len(), 6);
+    
+    let Some(Expression::FnCall(call)) = deduped.entry().map(|e| deduped.get(e)) else { panic!("expected a command") };
+    let Expression::Field(target, _) = deduped.get(call.pos_args[1]) else { panic!("expected a field") };
+    assert_eq!(call.pos_args[0], *target);
+    assert_eq!(&input[deduped.span(*target).range()], "+ 1 2");
+    
+    // Only identical subtrees are merged.
+    let mut block = crate::parse("echo (foo a=1 b=2) (foo a=2 b=1) (foo b=2 a=1)")?;
+    block.dedup();
+    assert_eq!(block.iter().filter(|(_, e)| matches!(e, Expression::FnCall(_))).count(), 3);
+    Ok(())
+}
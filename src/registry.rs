@@ -0,0 +1,291 @@
+//! A registry of host-provided commands, binding invocations to typed arguments.
+
+use std::rc::Rc;
+
+use rustc_hash::FxHashMap;
+use smartstring::alias::CompactString;
+use thiserror::Error;
+
+use crate::eval::{Arguments, Closure, CommandHost, Context, EvalError, Value};
+use crate::parser::CommandPath;
+
+pub mod signature;
+pub use signature::*;
+
+pub mod validate;
+pub use validate::*;
+
+pub mod types;
+pub use types::*;
+
+pub mod infer;
+pub use infer::*;
+
+/// The identifier of a command within a [`CommandRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CommandId(pub(crate) usize);
+
+/// The implementation of a command.
+pub trait CommandHandler {
+    /// Invokes the command with arguments bound to its signature.
+    fn invoke(&self, ctx: &mut Context, args: &BoundArgs) -> Result<Value, EvalError>;
+}
+
+impl<F> CommandHandler for F
+where
+    F: Fn(&mut Context, &BoundArgs) -> Result<Value, EvalError>
+{
+    fn invoke(&self, ctx: &mut Context, args: &BoundArgs) -> Result<Value, EvalError> {
+        self(ctx, args)
+    }
+}
+
+/// The implementation of a registered command.
+#[derive(Clone)]
+pub(crate) enum Implementation {
+    /// Implemented in Rust.
+    Native(Rc<dyn CommandHandler>),
+    /// Defined in IMPRAL, via `def` or `alias`.
+    Defined(Rc<Closure>),
+}
+
+/// A registered command.
+pub struct Command {
+    /// The signature of the command.
+    pub signature: Signature,
+    /// The implementation of the command.
+    pub(crate) implementation: Implementation,
+    /// How often the command was registered again, replacing it; see [`BoundCall`](crate::vm::BoundCall).
+    pub(crate) generation: u32,
+}
+
+impl Command {
+    /// Was the command defined in IMPRAL, instead of being implemented in Rust?
+    pub fn is_defined(&self) -> bool {
+        matches!(self.implementation, Implementation::Defined(_))
+    }
+    
+    /// May invocations of the command be folded ahead of time?
+    /// 
+    /// Only commands implemented in Rust can be declared pure.
+    pub fn is_pure(&self) -> bool {
+        self.signature.pure && !self.is_defined()
+    }
+}
+
+/// A registry of commands that can be invoked by the interpreter.
+pub struct CommandRegistry {
+    /// The registered commands.
+    pub(crate) commands: Vec<Command>,
+    /// Lookup table from command name to command.
+    pub(crate) names: FxHashMap<CompactString, CommandId>,
+    /// The schemas of the types of host objects, by name.
+    pub(crate) types: FxHashMap<CompactString, TypeSchema>,
+    /// How deeply defined commands may invoke each other.
+    pub(crate) recursion_limit: usize,
+    /// How deeply defined commands are currently invoking each other.
+    pub(crate) depth: usize,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            names: FxHashMap::default(),
+            types: FxHashMap::default(),
+            recursion_limit: Self::DEFAULT_RECURSION_LIMIT,
+            depth: 0,
+        }
+    }
+}
+
+impl CommandRegistry {
+    /// The default for [`CommandRegistry::set_recursion_limit`].
+    pub const DEFAULT_RECURSION_LIMIT: usize = 64;
+    
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Sets how deeply commands defined in IMPRAL may invoke each other (or themselves).
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+    }
+    
+    /// Registers a command, replacing any existing command of the same name.
+    pub fn register(
+        &mut self,
+        signature: Signature,
+        handler: impl CommandHandler + 'static
+    ) -> CommandId {
+        self.insert(Command {
+            signature,
+            implementation: Implementation::Native(Rc::new(handler)),
+            generation: 0,
+        })
+    }
+    
+    /// Registers a command implemented by a closure, replacing any existing command of the same name.
+    /// 
+    /// The closure takes every parameter of the signature by name.
+    pub fn register_defined(&mut self, signature: Signature, closure: Rc<Closure>) -> CommandId {
+        self.insert(Command {
+            signature,
+            implementation: Implementation::Defined(closure),
+            generation: 0,
+        })
+    }
+    
+    fn insert(&mut self, mut command: Command) -> CommandId {
+        if let Some(id) = self.names.get(&command.signature.name) {
+            command.generation = self.commands[id.0].generation.wrapping_add(1);
+            self.commands[id.0] = command;
+            return *id;
+        }
+        
+        let id = CommandId(self.commands.len());
+        self.names.insert(command.signature.name.clone(), id);
+        self.commands.push(command);
+        id
+    }
+    
+    /// Returns the identifier of the command with the given (full) name.
+    pub fn lookup(&self, name: &str) -> Option<CommandId> {
+        self.names.get(name).copied()
+    }
+    
+    /// Returns the identifier of the command with the given name, taking the imports into account.
+    /// 
+    /// After `use gfx::debug`, the command `gfx::debug::line` can be called as `line` or `debug::line`;
+    /// the full name always takes precedence, then the most recent imports.
+    pub fn resolve(&self, name: &str, imports: &[CommandPath]) -> Option<CommandId> {
+        if let Some(id) = self.lookup(name) {
+            return Some(id);
+        }
+        
+        let first = name.split(CommandPath::SEPARATOR).next().unwrap_or_default();
+        for import in imports.iter().rev() {
+            if let Some(id) = self.lookup(&format!("{import}{}{name}", CommandPath::SEPARATOR)) {
+                return Some(id);
+            }
+            
+            if import.is_qualified() && import.name() == first {
+                let namespace = import.namespace().join(CommandPath::SEPARATOR);
+                if let Some(id) = self.lookup(&format!("{namespace}{}{name}", CommandPath::SEPARATOR)) {
+                    return Some(id);
+                }
+            }
+        }
+        
+        None
+    }
+    
+    /// Returns the command with the given identifier.
+    pub fn get(&self, id: CommandId) -> &Command {
+        &self.commands[id.0]
+    }
+    
+    /// Returns the signature of the command with the given name.
+    pub fn signature(&self, name: &str) -> Option<&Signature> {
+        self.lookup(name).map(|id| &self.get(id).signature)
+    }
+    
+    /// Registers the schema of a type of host object, replacing any existing schema of the same name.
+    pub fn register_type(&mut self, schema: TypeSchema) {
+        self.types.insert(schema.name.clone(), schema);
+    }
+    
+    /// Returns the schema of the type of host object with the given name.
+    pub fn type_schema(&self, name: &str) -> Option<&TypeSchema> {
+        self.types.get(name)
+    }
+    
+    /// Returns an iterator over the names of all registered commands.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.commands.iter().map(|c| c.signature.name.as_str())
+    }
+    
+    /// Binds the arguments to the signature of the given command, and invokes it.
+    pub fn invoke(&mut self, ctx: &mut Context, id: CommandId, args: Arguments) -> Result<Value, EvalError> {
+        let bound = self.get(id).signature.bind(args)?;
+        let (values, rest) = (bound.values, bound.rest);
+        self.invoke_bound(ctx, id, values, rest)
+    }
+    
+    /// Invokes the given command with argument values that are already bound to its signature.
+    /// 
+    /// The `values` are in signature order, and must have been checked against their parameters.
+    pub(crate) fn invoke_bound(
+        &mut self,
+        ctx: &mut Context,
+        id: CommandId,
+        values: Vec<Value>,
+        rest: Vec<Value>
+    ) -> Result<Value, EvalError> {
+        let command = self.get(id);
+        let closure = match &command.implementation {
+            Implementation::Native(handler) => {
+                let bound = BoundArgs { signature: &command.signature, values, rest };
+                return handler.clone().invoke(ctx, &bound)
+            },
+            Implementation::Defined(closure) => closure.clone(),
+        };
+        
+        if self.depth >= self.recursion_limit {
+            return Err(EvalError::RecursionLimit(command.signature.name.clone(), self.recursion_limit));
+        }
+        
+        // Defined commands take every parameter by name.
+        let params = command.signature.positional.iter().chain(&command.signature.named);
+        let args = Arguments {
+            pos_args: Vec::new(),
+            nom_args: params.map(|p| p.name.clone()).zip(values).collect(),
+        };
+        
+        self.depth += 1;
+        let value = closure.call(self, ctx, args);
+        self.depth -= 1;
+        value
+    }
+}
+
+impl CommandHost for CommandRegistry {
+    fn call(&mut self, ctx: &mut Context, name: &str, args: Arguments) -> Result<Value, EvalError> {
+        match self.resolve(name, &ctx.imports) {
+            Some(id) => self.invoke(ctx, id, args),
+            None => Err(EvalError::UnknownCommand(name.into())),
+        }
+    }
+    
+    fn define(&mut self, _ctx: &mut Context, signature: Signature, closure: Rc<Closure>) -> Result<(), EvalError> {
+        self.register_defined(signature, closure);
+        Ok(())
+    }
+}
+
+/// An error binding arguments to a [`Signature`].
+#[derive(Error, Debug)]
+pub enum BindError {
+    /// A required argument was not given.
+    #[error("Command '{0}' is missing the argument '{1}'")]
+    Missing(CompactString, CompactString),
+    
+    /// More positional arguments were given than the command accepts.
+    #[error("Command '{0}' takes at most {1} positional arguments")]
+    Extra(CompactString, usize),
+    
+    /// A named argument was given that the command does not know.
+    #[error("Command '{0}' has no parameter named '{1}'")]
+    Unknown(CompactString, CompactString),
+    
+    /// An argument was of the wrong kind.
+    #[error("Argument '{1}' of command '{0}' expects a {2}, but got a {3}")]
+    Mismatch(CompactString, CompactString, &'static str, &'static str),
+}
+
+#[cfg(test)]
+pub(crate) mod testing;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,325 @@
+//! Static type inference over the expressions of a block.
+
+use super::*;
+use crate::lexer::Literal;
+use crate::parser::{Arrow, Block, BlockRef, Expression, FnCall, Pipe, PipeSeg};
+
+/// The statically inferred types of the expressions of a block; see [`infer()`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Types {
+    /// The type of every expression, by [`BlockRef::index`]; `None` if unreachable from the entry.
+    types: Vec<Option<Type>>,
+}
+
+impl Types {
+    /// Returns the inferred type of the given expression; `Any` if it is unreachable.
+    pub fn get(&self, br: BlockRef) -> &Type {
+        static ANY: Type = Type::Any;
+        self.types.get(br.index()).and_then(Option::as_ref).unwrap_or(&ANY)
+    }
+    
+    /// Returns true if the given expression is reachable from the entry of the block.
+    pub fn is_reachable(&self, br: BlockRef) -> bool {
+        matches!(self.types.get(br.index()), Some(Some(_)))
+    }
+}
+
+/// Infers the type of every expression in the block, without evaluating anything.
+/// 
+/// Command results are typed via [`Signature::returns`](Signature#structfield.returns),
+/// and the fields, elements and items of host objects via the registered [`TypeSchema`]s.
+/// The result reference (`$`) takes on the type of whatever is passed on to it,
+/// such as the items of a pipe; variables take on the type last assigned to them.
+/// 
+/// Every occurrence of a reference is typed on its own; only expressions shared between several parents,
+/// as left by [`Block::dedup`], get the type covering all of their uses.
+pub fn infer(block: &Block, registry: &CommandRegistry) -> Types {
+    let mut inferer = Inferer {
+        registry,
+        block,
+        imports: Vec::new(),
+        locals: FxHashMap::default(),
+        types: vec![None; block.len()],
+    };
+    if let Some(entry) = block.entry() {
+        inferer.expr(entry, &Type::Any);
+    }
+    Types { types: inferer.types }
+}
+
+/// The state of a type inference.
+struct Inferer<'r, 'b> {
+    /// The registry providing command signatures and type schemas.
+    registry: &'r CommandRegistry,
+    /// The block whose expressions are inferred.
+    block: &'b Block,
+    /// The namespaces and commands imported so far.
+    imports: Vec<CommandPath>,
+    /// The types of the variables assigned so far.
+    locals: FxHashMap<CompactString, Type>,
+    /// The types inferred so far.
+    types: Vec<Option<Type>>,
+}
+
+impl Inferer<'_, '_> {
+    /// Records the type of an expression, covering any type it was already given.
+    fn record(&mut self, br: BlockRef, ty: Type) -> Type {
+        let slot = &mut self.types[br.index()];
+        *slot = Some(match slot.take() {
+            Some(previous) => previous.join(ty.clone()),
+            None => ty.clone(),
+        });
+        ty
+    }
+    
+    /// Infers the type of an expression, with `$` being of the given type.
+    fn expr(&mut self, br: BlockRef, result: &Type) -> Type {
+        let ty = match self.block.get(br) {
+            Expression::Empty => Type::Nil,
+            Expression::Value(literal) => match literal {
+                Literal::RefRes => result.clone(),
+                Literal::RefVar(name) => self.locals.get(name).cloned().unwrap_or_default(),
+                literal => Type::of_literal(literal),
+            },
+            Expression::FnCall(call) => self.call(call, None, result),
+            Expression::Range(start, end, _) => {
+                let start = self.expr(*start, result);
+                let end = self.expr(*end, result);
+                Type::Range(Box::new(start.join(end)))
+            },
+            Expression::Field(target, name) => {
+                let target = self.expr(*target, result);
+                self.field(&target, name)
+            },
+            Expression::Index(target, index) => {
+                let target = self.expr(*target, result);
+                let index = self.expr(*index, result);
+                self.index(&target, &index)
+            },
+            Expression::Method(target, call) => {
+                let target = self.expr(*target, result);
+                match self.block.get(*call) {
+                    Expression::FnCall(fn_call) => {
+                        let ty = self.call(fn_call, Some(target), result);
+                        self.record(*call, ty)
+                    },
+                    _ => self.expr(*call, result),
+                }
+            },
+            // `?!` throws on null, while `?` turns errors into null.
+            Expression::Try(target, throw) => match self.expr(*target, result) {
+                ty if *throw => ty.non_null(),
+                ty => ty.nullable(),
+            },
+            Expression::Pipe(pipe) => self.pipe(pipe, result),
+            Expression::Arrow(arrow, left, right) => self.arrow(*arrow, *left, *right, result),
+            Expression::Sequence(statements) => {
+                let mut ty = Type::Nil;
+                let mut result = result.clone();
+                for statement in statements {
+                    ty = self.expr(*statement, &result);
+                    result = ty.clone();
+                }
+                ty
+            },
+            Expression::Code(_) => Type::Closure,
+            Expression::Def(def) => {
+                for default in def.named.iter().filter_map(|(_, default)| *default) {
+                    self.expr(default, result);
+                }
+                Type::Nil
+            },
+            Expression::Error => Type::Any,
+        };
+        self.record(br, ty)
+    }
+    
+    /// Infers the result type of an invocation, optionally with a receiver as first positional argument.
+    fn call(&mut self, call: &FnCall, receiver: Option<Type>, result: &Type) -> Type {
+        // Mirrors `Interpreter::eval_intrinsic`.
+        let intrinsic = match call.name.segments() {
+            [name] if receiver.is_none() => name.as_str(),
+            _ => "",
+        };
+        match intrinsic {
+            "list" => {
+                let items = call.pos_args.iter().map(|arg| self.expr(*arg, result));
+                return Type::list(items.reduce(Type::join).unwrap_or_default())
+            },
+            "dict" => {
+                let values = call.nom_args.values().map(|arg| self.expr(*arg, result));
+                return Type::dict(values.reduce(Type::join).unwrap_or_default())
+            },
+            "if-then" if call.pos_args.len() == 2 => {
+                let condition = self.expr(call.pos_args[0], result);
+                let then = self.expr(call.pos_args[1], &condition.clone().non_null());
+                // A falsy condition is passed on as-is.
+                return match condition {
+                    Type::Any | Type::Nil => condition,
+                    Type::Bool => Type::Bool.join(then),
+                    Type::Nullable(inner) if *inner == Type::Bool => Type::Bool.join(then).nullable(),
+                    Type::Nullable(_) => then.nullable(),
+                    _ => then,
+                }
+            },
+            "if-else" if call.pos_args.len() == 2 => {
+                let value = self.expr(call.pos_args[0], result);
+                let otherwise = self.expr(call.pos_args[1], result);
+                return match value {
+                    Type::Nil => otherwise,
+                    value => value.non_null().join(otherwise),
+                }
+            },
+            "use" if call.nom_args.is_empty() => {
+                for arg in &call.pos_args {
+                    if let Expression::Value(Literal::Str(path)) = self.block.get(*arg) {
+                        self.imports.push(path.clone().into());
+                    }
+                    self.expr(*arg, result);
+                }
+                return Type::Nil
+            },
+            _ => (),
+        }
+        
+        for arg in call.pos_args.iter().chain(call.nom_args.values()) {
+            self.expr(*arg, result);
+        }
+        
+        // Closures are invoked without the host, and thus have no signature.
+        if call.name == "call" {
+            return Type::Any
+        }
+        
+        match self.registry.resolve(&call.name.to_str(), &self.imports) {
+            Some(id) => self.registry.get(id).signature.returns.clone(),
+            None => Type::Any,
+        }
+    }
+    
+    /// Infers the result type of a pipe, passing the item type on from stage to stage.
+    fn pipe(&mut self, pipe: &Pipe, result: &Type) -> Type {
+        let source = self.expr(pipe.source, result);
+        let mut item = self.items(&source);
+        
+        for seg in &pipe.stages {
+            item = match seg {
+                PipeSeg::Mapping { mapper } => match self.expr(*mapper, &item) {
+                    // Lazy iterables are flattened into the pipe.
+                    ty @ (Type::Range(_) | Type::Object(_)) => self.items(&ty),
+                    ty => ty,
+                },
+                PipeSeg::Exclude { predicate } | PipeSeg::Finding { predicate } => {
+                    self.expr(*predicate, &item);
+                    item
+                },
+                PipeSeg::Folding { initial, reducer } => {
+                    let initial = self.expr(*initial, &Type::Nil);
+                    let previous = self.locals.insert("acc".into(), initial.clone());
+                    let reduced = self.expr(*reducer, &item);
+                    match previous {
+                        Some(previous) => self.locals.insert("acc".into(), previous),
+                        None => self.locals.remove("acc"),
+                    };
+                    initial.join(reduced)
+                },
+                PipeSeg::Collect { collector } => match self.expr(*collector, &Type::Nil) {
+                    Type::List(items) => Type::List(Box::new(items.join(item))),
+                    Type::Dict(_) => Type::dict(Type::Any),
+                    Type::Str => Type::Str,
+                    _ => Type::Any,
+                },
+            };
+        }
+        
+        // Mirrors `Interpreter::eval_pipe`.
+        match pipe.stages.last() {
+            Some(PipeSeg::Finding { .. }) => item.nullable(),
+            Some(PipeSeg::Folding { .. } | PipeSeg::Collect { .. }) => item,
+            _ => Type::list(item),
+        }
+    }
+    
+    /// Infers the result type of an arrow, passing the type of the left expression on to the right one.
+    fn arrow(&mut self, arrow: Arrow, left: BlockRef, right: BlockRef, result: &Type) -> Type {
+        let value = self.expr(left, result);
+        let target = match self.block.get(right) {
+            Expression::Value(Literal::RefVar(name)) => Some(name.clone()),
+            _ => None,
+        };
+        
+        match (arrow, target) {
+            (Arrow::Assign, Some(name)) => {
+                self.locals.insert(name, value.clone());
+                self.record(right, value.clone());
+                value
+            },
+            (Arrow::Append, Some(name)) => {
+                let list = match self.locals.remove(&name) {
+                    None | Some(Type::Nil) => Type::list(value.clone()),
+                    Some(Type::List(items)) => Type::List(Box::new(items.join(value.clone()))),
+                    Some(_) => Type::Any,
+                };
+                self.locals.insert(name, list.clone());
+                self.record(right, list);
+                value
+            },
+            (Arrow::Assign | Arrow::Append, None) => {
+                self.expr(right, result);
+                value
+            },
+            (Arrow::Bind, _) => self.expr(right, &value),
+            (Arrow::Defer, _) => {
+                self.expr(right, &value);
+                value
+            },
+            (Arrow::Grid, _) => self.expr(right, &Type::list(Type::list(Type::Any))),
+        }
+    }
+    
+    /// Returns the type of the items of a pipe with a source of the given type.
+    /// 
+    /// Mirrors [`into_items`](crate::eval::into_items).
+    fn items(&self, ty: &Type) -> Type {
+        match ty {
+            Type::List(item) => (**item).clone(),
+            Type::Dict(value) => Type::list(Type::Str.join((**value).clone())),
+            Type::Range(bound) => (**bound).clone(),
+            Type::Nullable(inner) => self.items(inner),
+            Type::Object(Some(name)) => self.registry.type_schema(name)
+                .map(|schema| schema.items.clone())
+                .unwrap_or_default(),
+            Type::Object(None) | Type::Nil => Type::Any,
+            ty => ty.clone(),
+        }
+    }
+    
+    /// Returns the type of the named field of a value of the given type.
+    /// 
+    /// Mirrors `Interpreter::eval_field`.
+    fn field(&self, ty: &Type, name: &str) -> Type {
+        match (ty, name) {
+            (Type::Dict(value), _) => (**value).clone(),
+            (Type::Range(bound), "start" | "end") => (**bound).clone(),
+            (Type::Object(Some(object)), name) => self.registry.type_schema(object)
+                .and_then(|schema| schema.fields.get(name).cloned())
+                .unwrap_or_default(),
+            _ => Type::Any,
+        }
+    }
+    
+    /// Returns the type of the elements of a value of the given type, accessed by an index of the given type.
+    /// 
+    /// Mirrors `Interpreter::eval_index`.
+    fn index(&self, ty: &Type, index: &Type) -> Type {
+        match (ty, index) {
+            (Type::List(item), Type::Int) => (**item).clone(),
+            (Type::Dict(value), Type::Str) => (**value).clone(),
+            (Type::Str, Type::Int) => Type::Str,
+            (Type::Object(Some(object)), _) => self.registry.type_schema(object)
+                .map(|schema| schema.index.clone())
+                .unwrap_or_default(),
+            _ => Type::Any,
+        }
+    }
+}
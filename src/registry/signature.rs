@@ -0,0 +1,402 @@
+//! Command signatures and argument binding.
+
+use super::*;
+
+/// The kind of value a parameter accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Accepts any value.
+    Any,
+    /// Accepts only `null`.
+    Nil,
+    /// Accepts booleans.
+    Bool,
+    /// Accepts integer numbers.
+    Int,
+    /// Accepts decimal numbers.
+    Dec,
+    /// Accepts both integer and decimal numbers.
+    Number,
+    /// Accepts unique identifiers.
+    Uid,
+    /// Accepts strings.
+    Str,
+    /// Accepts byte-strings.
+    Byt,
+    /// Accepts object references (`@…`).
+    ObjRef,
+    /// Accepts lists.
+    List,
+    /// Accepts dicts.
+    Dict,
+    /// Accepts ranges.
+    Range,
+    /// Accepts objects provided by the host.
+    Object,
+    /// Accepts closures.
+    Closure,
+}
+
+impl Kind {
+    /// Returns the kind of the given value.
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Nil => Kind::Nil,
+            Value::Bool(_) => Kind::Bool,
+            Value::Int(_) => Kind::Int,
+            Value::Dec(_) => Kind::Dec,
+            Value::Uid(_) => Kind::Uid,
+            Value::Str(_) => Kind::Str,
+            Value::Byt(_) => Kind::Byt,
+            Value::ObjIdx(_) | Value::ObjUid(_) | Value::ObjKey(_) => Kind::ObjRef,
+            Value::List(_) => Kind::List,
+            Value::Dict(_) => Kind::Dict,
+            Value::Range(_) => Kind::Range,
+            Value::Object(_) => Kind::Object,
+            Value::Closure(_) => Kind::Closure,
+        }
+    }
+    
+    /// Does a parameter of this kind accept the given value?
+    pub fn accepts(&self, value: &Value) -> bool {
+        self.accepts_kind(Kind::of(value))
+    }
+    
+    /// Does a parameter of this kind accept every value of the given kind?
+    pub fn accepts_kind(&self, kind: Kind) -> bool {
+        match (self, kind) {
+            (Kind::Any, _) => true,
+            (Kind::Number, Kind::Int | Kind::Dec) => true,
+            (expected, actual) => *expected == actual,
+        }
+    }
+    
+    /// Returns the name of the kind as static str.
+    pub const fn get_type_str(&self) -> &'static str {
+        match self {
+            Kind::Any => "anything",
+            Kind::Nil => "nil",
+            Kind::Bool => "boolean",
+            Kind::Int => "integer-number",
+            Kind::Dec => "decimal-number",
+            Kind::Number => "number",
+            Kind::Uid => "unique-identifier",
+            Kind::Str => "char-string",
+            Kind::Byt => "byte-string",
+            Kind::ObjRef => "object reference",
+            Kind::List => "list",
+            Kind::Dict => "dict",
+            Kind::Range => "range",
+            Kind::Object => "host object",
+            Kind::Closure => "closure",
+        }
+    }
+}
+
+/// A parameter of a command.
+#[derive(Debug, Clone)]
+pub struct Param {
+    /// The name of the parameter.
+    pub name: CompactString,
+    
+    /// The kind of value the parameter accepts.
+    pub kind: Kind,
+    
+    /// The value used when no argument is given; required if `None`.
+    pub default: Option<Value>,
+    
+    /// Is the parameter a flag, written as `+name`/`-name`?
+    pub flag: bool,
+}
+
+/// The signature of a command, describing the arguments it takes.
+#[derive(Debug, Clone, Default)]
+pub struct Signature {
+    /// The name of the command.
+    pub name: CompactString,
+    
+    /// The positional parameters, in order.
+    pub positional: Vec<Param>,
+    
+    /// The named parameters, including flags.
+    pub named: Vec<Param>,
+    
+    /// The parameter receiving all remaining positional arguments, if any.
+    pub variadic: Option<Param>,
+    
+    /// Does the command always return the same value for the same arguments, without side effects?
+    /// 
+    /// Invocations of pure commands with only literal arguments can be folded ahead of time.
+    pub pure: bool,
+    
+    /// The type of the value the command returns.
+    pub returns: Type,
+}
+
+impl Signature {
+    /// Creates a new signature without any parameters.
+    pub fn new(name: impl Into<CompactString>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+    
+    /// Adds a required positional parameter.
+    #[must_use]
+    pub fn positional(mut self, name: impl Into<CompactString>, kind: Kind) -> Self {
+        self.positional.push(Param { name: name.into(), kind, default: None, flag: false });
+        self
+    }
+    
+    /// Adds a positional parameter with a default value.
+    #[must_use]
+    pub fn optional(mut self, name: impl Into<CompactString>, kind: Kind, default: impl Into<Value>) -> Self {
+        self.positional.push(Param { name: name.into(), kind, default: Some(default.into()), flag: false });
+        self
+    }
+    
+    /// Adds a named parameter with a default value.
+    #[must_use]
+    pub fn named(mut self, name: impl Into<CompactString>, kind: Kind, default: impl Into<Value>) -> Self {
+        self.named.push(Param { name: name.into(), kind, default: Some(default.into()), flag: false });
+        self
+    }
+    
+    /// Adds a boolean flag, which is `false` unless given as `+name`.
+    #[must_use]
+    pub fn flag(mut self, name: impl Into<CompactString>) -> Self {
+        self.named.push(Param { name: name.into(), kind: Kind::Bool, default: Some(Value::Bool(false)), flag: true });
+        self
+    }
+    
+    /// Sets the parameter that receives all remaining positional arguments.
+    #[must_use]
+    pub fn variadic(mut self, name: impl Into<CompactString>, kind: Kind) -> Self {
+        self.variadic = Some(Param { name: name.into(), kind, default: None, flag: false });
+        self
+    }
+    
+    /// Declares the command as pure; see [`Signature::pure`](#structfield.pure).
+    #[must_use]
+    pub fn pure(mut self) -> Self {
+        self.pure = true;
+        self
+    }
+    
+    /// Sets the type of the value the command returns; `Any` by default.
+    #[must_use]
+    pub fn returns(mut self, ty: impl Into<Type>) -> Self {
+        self.returns = ty.into();
+        self
+    }
+    
+    /// Returns the named parameter (or flag) with the given name.
+    pub fn get_named(&self, name: &str) -> Option<&Param> {
+        self.named.iter().find(|p| p.name == name)
+    }
+    
+    /// Binds the given arguments to the parameters of this signature.
+    pub fn bind(&self, args: Arguments) -> Result<BoundArgs<'_>, BindError> {
+        let mut values = Vec::with_capacity(self.positional.len() + self.named.len());
+        let mut rest = Vec::new();
+        
+        let mut pos_args = args.pos_args.into_iter();
+        for param in &self.positional {
+            let value = match pos_args.next() {
+                Some(value) => value,
+                None => match &param.default {
+                    Some(default) => default.clone(),
+                    None => return Err(BindError::Missing(self.name.clone(), param.name.clone())),
+                },
+            };
+            values.push(self.check(param, value)?);
+        }
+        
+        for (index, value) in pos_args.enumerate() {
+            match &self.variadic {
+                Some(param) => rest.push(self.check(param, value)?),
+                None => return Err(BindError::Extra(self.name.clone(), self.positional.len() + index)),
+            }
+        }
+        
+        let mut nom_args = args.nom_args;
+        for param in &self.named {
+            let value = match nom_args.remove(&param.name) {
+                Some(value) => value,
+                None => match &param.default {
+                    Some(default) => default.clone(),
+                    None => return Err(BindError::Missing(self.name.clone(), param.name.clone())),
+                },
+            };
+            values.push(self.check(param, value)?);
+        }
+        
+        // Anything left over is unknown to this signature.
+        if let Some(key) = nom_args.into_keys().min() {
+            return Err(BindError::Unknown(self.name.clone(), key));
+        }
+        
+        Ok(BoundArgs { signature: self, values, rest })
+    }
+    
+    /// Checks that the parameter accepts the given value.
+    pub(crate) fn check(&self, param: &Param, value: Value) -> Result<Value, BindError> {
+        if param.kind.accepts(&value) {
+            Ok(value)
+        } else {
+            Err(BindError::Mismatch(self.name.clone(), param.name.clone(), param.kind.get_type_str(), value.get_type_str()))
+        }
+    }
+}
+
+/// Arguments that have been bound to the parameters of a [`Signature`].
+#[derive(Debug)]
+pub struct BoundArgs<'s> {
+    /// The signature the arguments were bound to.
+    pub(crate) signature: &'s Signature,
+    /// The positional, then named, argument values; in signature order.
+    pub(crate) values: Vec<Value>,
+    /// The variadic arguments.
+    pub(crate) rest: Vec<Value>,
+}
+
+impl BoundArgs<'_> {
+    /// Returns the signature the arguments were bound to.
+    pub fn signature(&self) -> &Signature {
+        self.signature
+    }
+    
+    /// Returns the value of the parameter with the given name.
+    pub fn value(&self, name: &str) -> Option<&Value> {
+        self.signature.positional.iter()
+            .chain(self.signature.named.iter())
+            .position(|p| p.name == name)
+            .map(|i| &self.values[i])
+    }
+    
+    /// Returns the value of the parameter with the given name, converted into a Rust type.
+    pub fn get<T: FromValue>(&self, name: &str) -> Result<T, BindError> {
+        let value = self.value(name)
+            .ok_or_else(|| BindError::Missing(self.signature.name.clone(), name.into()))?;
+        T::from_value(value)
+            .ok_or_else(|| BindError::Mismatch(self.signature.name.clone(), name.into(), T::KIND.get_type_str(), value.get_type_str()))
+    }
+    
+    /// Returns true if the flag with the given name was set.
+    pub fn flag(&self, name: &str) -> bool {
+        matches!(self.value(name), Some(Value::Bool(true)))
+    }
+    
+    /// Returns the variadic arguments.
+    pub fn rest(&self) -> &[Value] {
+        &self.rest
+    }
+    
+    /// Returns the variadic arguments, converted into a Rust type.
+    pub fn rest_as<T: FromValue>(&self) -> Result<Vec<T>, BindError> {
+        let name = self.signature.variadic.as_ref().map(|p| p.name.clone()).unwrap_or_default();
+        self.rest.iter()
+            .map(|value| T::from_value(value)
+                .ok_or_else(|| BindError::Mismatch(self.signature.name.clone(), name.clone(), T::KIND.get_type_str(), value.get_type_str()))
+            )
+            .collect()
+    }
+}
+
+/// Conversion of runtime values into Rust types.
+pub trait FromValue: Sized {
+    /// The kind of value this type is converted from.
+    const KIND: Kind;
+    
+    /// Converts the value, returning `None` if it is of the wrong kind.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    const KIND: Kind = Kind::Any;
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromValue for bool {
+    const KIND: Kind = Kind::Bool;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for i64 {
+    const KIND: Kind = Kind::Int;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    const KIND: Kind = Kind::Number;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(v) => Some(*v as f64),
+            Value::Dec(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for CompactString {
+    const KIND: Kind = Kind::Str;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Str(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const KIND: Kind = Kind::Str;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Str(v) => Some(v.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Vec<Value> {
+    const KIND: Kind = Kind::List;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::List(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    const KIND: Kind = T::KIND;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl FromValue for Rc<crate::eval::Closure> {
+    const KIND: Kind = Kind::Closure;
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Closure(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
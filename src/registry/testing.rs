@@ -0,0 +1,42 @@
+//! Commands shared by the tests of the registry, the optimizer and the virtual machine.
+
+use super::*;
+
+/// Returns a registry with the commands every test can rely on; tests register their own on top.
+pub(crate) fn registry() -> CommandRegistry {
+    let mut registry = CommandRegistry::new();
+    
+    registry.register(
+        Signature::new("heal")
+            .positional("target", Kind::Str)
+            .named("amount", Kind::Int, 10)
+            .flag("silent")
+            .returns(Kind::Str),
+        |_: &mut Context, args: &BoundArgs| {
+            let target: CompactString = args.get("target")?;
+            let amount: i64 = args.get("amount")?;
+            let silent = if args.flag("silent") {"silently"} else {"loudly"};
+            Ok(Value::Str(format!("{target} +{amount} {silent}").into()))
+        }
+    );
+    
+    registry.register(
+        Signature::new("sum")
+            .variadic("numbers", Kind::Number)
+            .returns(Kind::Dec),
+        |_: &mut Context, args: &BoundArgs| {
+            Ok(Value::Dec(args.rest_as::<f64>()?.into_iter().sum()))
+        }
+    );
+    
+    registry.register(
+        Signature::new("+")
+            .variadic("numbers", Kind::Int)
+            .pure(),
+        |_: &mut Context, args: &BoundArgs| {
+            Ok(Value::Int(args.rest_as::<i64>()?.into_iter().sum()))
+        }
+    );
+    
+    registry
+}
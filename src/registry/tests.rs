@@ -0,0 +1,257 @@
+//! Tests to ensure commands are bound correctly.
+
+use super::*;
+use super::testing::registry;
+
+fn run(input: &str) -> Result<Value, EvalError> {
+    let block = crate::parse(input).expect("input should parse");
+    crate::eval::eval(&block, &mut registry(), &mut Context::default())
+}
+
+#[test]
+fn bind_arguments() -> Result<(), EvalError> {
+    assert_eq!(run("heal bob")?, "bob +10 loudly".into());
+    assert_eq!(run("heal bob amount=5 +silent")?, "bob +5 silently".into());
+    assert_eq!(run("heal bob -silent")?, "bob +10 loudly".into());
+    assert_eq!(run("sum 1 2 0.5")?, Value::Dec(3.5));
+    assert_eq!(run("sum")?, Value::Dec(0.0));
+    Ok(())
+}
+
+#[test]
+fn bind_errors() {
+    assert!(matches!(run("heal"), Err(EvalError::Bind(BindError::Missing(_, p))) if p == "target"));
+    assert!(matches!(run("heal bob bob"), Err(EvalError::Bind(BindError::Extra(_, 1)))));
+    assert!(matches!(run("heal bob foo=1"), Err(EvalError::Bind(BindError::Unknown(_, p))) if p == "foo"));
+    assert!(matches!(run("heal 1"), Err(EvalError::Bind(BindError::Mismatch(_, p, _, _))) if p == "target"));
+    assert!(matches!(run("heal bob amount=fast"), Err(EvalError::Bind(BindError::Mismatch(_, p, _, _))) if p == "amount"));
+    assert!(matches!(run("sum 1 two"), Err(EvalError::Bind(BindError::Mismatch(_, p, _, _))) if p == "numbers"));
+    assert!(matches!(run("hurt bob"), Err(EvalError::UnknownCommand(_))));
+}
+
+#[test]
+fn namespaced_commands() -> Result<(), EvalError> {
+    let mut registry = registry();
+    registry.register(Signature::new("world::spawn"), |_: &mut Context, _: &BoundArgs| Ok("spawned".into()));
+    registry.register(Signature::new("gfx::debug::line"), |_: &mut Context, _: &BoundArgs| Ok("line".into()));
+    
+    let mut ctx = Context::default();
+    let mut run = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("input should parse");
+        crate::eval::eval(&block, &mut registry, &mut ctx)
+    };
+    
+    assert_eq!(run("world::spawn")?, "spawned".into());
+    assert!(matches!(run("spawn"), Err(EvalError::UnknownCommand(_))));
+    assert!(matches!(run("debug::line"), Err(EvalError::UnknownCommand(_))));
+    
+    assert_eq!(run("use world gfx::debug; spawn")?, "spawned".into());
+    assert_eq!(run("line")?, "line".into());
+    assert_eq!(run("debug::line")?, "line".into());
+    assert_eq!(run("heal bob")?, "bob +10 loudly".into());
+    Ok(())
+}
+
+#[test]
+fn closure_callbacks() -> Result<(), EvalError> {
+    use std::cell::RefCell;
+    use crate::eval::Closure;
+    
+    let scheduled: Rc<RefCell<Vec<Rc<Closure>>>> = Default::default();
+    let mut registry = registry();
+    registry.register(
+        Signature::new("schedule")
+            .positional("callback", Kind::Closure),
+        {
+            let scheduled = scheduled.clone();
+            move |_: &mut Context, args: &BoundArgs| {
+                scheduled.borrow_mut().push(args.get("callback")?);
+                Ok(Value::Nil)
+            }
+        }
+    );
+    
+    let block = crate::parse("schedule {|$amount| heal $who amount=$amount}").expect("input should parse");
+    let mut ctx = Context::default();
+    ctx.locals.insert("who".into(), "bob".into());
+    crate::eval::eval(&block, &mut registry, &mut ctx)?;
+    
+    let callback = scheduled.borrow_mut().pop().expect("a callback should be scheduled");
+    let args = Arguments { pos_args: vec![Value::Int(3)], ..Default::default() };
+    assert_eq!(callback.call(&mut registry, &mut Context::default(), args)?, "bob +3 loudly".into());
+    
+    let block = crate::parse("schedule 1").expect("input should parse");
+    let result = crate::eval::eval(&block, &mut registry, &mut ctx);
+    assert!(matches!(result, Err(EvalError::Bind(BindError::Mismatch(_, p, _, _))) if p == "callback"));
+    Ok(())
+}
+
+#[test]
+fn defined_commands() -> Result<(), EvalError> {
+    let mut defined = registry();
+    let mut ctx = Context::default();
+    let mut run = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("input should parse");
+        crate::eval::eval(&block, &mut defined, &mut ctx)
+    };
+    
+    run("def revive $who amount=5: heal $who amount=$amount")?;
+    assert_eq!(run("revive bob")?, "bob +5 loudly".into());
+    assert_eq!(run("revive bob amount=7")?, "bob +7 loudly".into());
+    assert!(matches!(run("revive"), Err(EvalError::Bind(BindError::Missing(_, p))) if p == "who"));
+    assert_eq!(run("def half-sum $a b=0: sum $a $b 0.5; half-sum 1")?, Value::Dec(1.5));
+    
+    run("alias quietly: heal _ amount=_ +silent")?;
+    assert_eq!(run("quietly bob amount=2")?, "bob +2 silently".into());
+    assert!(matches!(run("quietly bob"), Err(EvalError::Bind(BindError::Missing(_, p))) if p == "amount"));
+    
+    // Defaults are evaluated once, and locals are captured at definition.
+    assert_eq!(run("sum 3 -> $n; def add-n $x: sum $x $n; sum 0 -> $n; add-n 1")?, Value::Dec(4.0));
+    
+    run("def forever $n: forever $n")?;
+    assert!(matches!(run("forever 1"), Err(EvalError::RecursionLimit(name, 64)) if name == "forever"));
+    
+    let mut limited = registry();
+    limited.set_recursion_limit(3);
+    let block = crate::parser::parse_script("def forever: forever; forever").expect("input should parse");
+    let result = crate::eval::eval(&block, &mut limited, &mut Context::default());
+    assert!(matches!(result, Err(EvalError::RecursionLimit(_, 3))));
+    assert!(limited.lookup("forever").is_some_and(|id| limited.get(id).is_defined()));
+    assert!(!limited.lookup("heal").is_some_and(|id| limited.get(id).is_defined()));
+    Ok(())
+}
+
+#[test]
+fn validate_commands() {
+    let registry = registry();
+    let validate = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("input should parse");
+        validate(&block, &registry)
+    };
+    
+    assert_eq!(validate("heal bob amount=5 +silent; sum 1 2.5"), []);
+    assert_eq!(validate("heal $name amount=$x; sum $y (heal bob)"), []);
+    assert_eq!(validate("sum 1 2 | heal $; sum 1 && heal $; sum 1 => heal $; sum 2; heal $"), []);
+    assert_eq!(validate("def revive $who amount=5: heal $who amount=$amount; revive bob amount=1"), []);
+    assert_eq!(validate("list {|| heal $} 1"), []);
+    
+    let errors = validate("hael bob; sun 1; frobnicate");
+    assert!(matches!(&errors[..], [
+        ValidationError::UnknownCommand(_, a, Some(x)),
+        ValidationError::UnknownCommand(_, b, Some(y)),
+        ValidationError::UnknownCommand(_, c, None),
+    ] if a == "hael" && x == "heal" && b == "sun" && y == "sum" && c == "frobnicate"));
+    
+    let errors = validate("heal; heal bob bob; list bob.(heal bob)");
+    assert!(matches!(&errors[..], [
+        ValidationError::Missing(_, _, p),
+        ValidationError::Extra(span, _, 1),
+        ValidationError::Extra(_, _, 1),
+    ] if p == "target" && span.range() == (15..18)));
+    
+    let errors = validate("heal bob foo=1 amount=1 amount=2 +amount");
+    assert!(matches!(&errors[..], [
+        ValidationError::Duplicate(_, _, a),
+        ValidationError::NotAFlag(_, _, b),
+        ValidationError::Unknown(_, _, c),
+    ] if a == "amount" && b == "amount" && c == "foo"));
+    
+    let errors = validate("heal 1 amount=fast; sum 1 two [3]; heal bob amount=1.5");
+    let mismatches: Vec<_> = errors.iter().map(|e| match e {
+        ValidationError::Mismatch(span, _, param, expected, got) => format!("{param}@{}: {expected} <- {got}", span.start),
+        e => panic!("expected a mismatch, got {e:?}"),
+    }).collect();
+    assert_eq!(mismatches, [
+        "target@5: char-string <- integer-number",
+        "amount@14: integer-number <- char-string",
+        "numbers@26: number <- char-string",
+        "numbers@30: number <- list",
+        "amount@51: integer-number <- decimal-number",
+    ]);
+    
+    // Only the first statement lacks a result; `||` passes none on either.
+    for (input, start) in [("heal $", 5), ("sum 1 || heal $", 14), ("list $ | heal $", 5)] {
+        let errors = validate(input);
+        assert!(matches!(&errors[..], [
+            ValidationError::StrayResult(span)
+        ] if span.start == start), "{input}: {errors:?}");
+    }
+    
+    // Definitions are resolved in order, but may call themselves.
+    let errors = validate("revive bob; def revive $who: heal $who");
+    assert!(matches!(&errors[..], [ValidationError::UnknownCommand(span, name, _)] if name == "revive" && span.range() == (0..10)), "{errors:?}");
+    assert_eq!(validate("def countdown $n: countdown $n; countdown 3"), []);
+    
+    // Imports are resolved in order.
+    let mut registry = self::registry();
+    registry.register(Signature::new("world::spawn"), |_: &mut Context, _: &BoundArgs| Ok(Value::Nil));
+    let block = crate::parser::parse_script("world::spawn; use world; spawn").expect("input should parse");
+    assert_eq!(crate::registry::validate(&block, &registry), []);
+    let block = crate::parser::parse_script("spawn; use world").expect("input should parse");
+    assert!(matches!(&crate::registry::validate(&block, &registry)[..], [ValidationError::UnknownCommand(_, _, Some(s))] if s == "world::spawn"));
+}
+
+#[test]
+fn infer_types() {
+    let mut registry = registry();
+    registry.register(Signature::new("player").returns(Type::object("player")), |_: &mut Context, _: &BoundArgs| Ok(Value::Nil));
+    registry.register_type(TypeSchema::new("player")
+        .field("name", Kind::Str)
+        .field("health", Kind::Int)
+        .index(Type::object("item"))
+        .items(Type::object("item")));
+    registry.register_type(TypeSchema::new("item").field("weight", Kind::Dec));
+    
+    let infer = |input: &str| {
+        let block = crate::parser::parse_script(input).expect("input should parse");
+        let types = infer(&block, &registry);
+        types.get(block.entry().expect("block should have an entry")).to_string()
+    };
+    
+    assert_eq!(infer("heal bob"), "char-string");
+    assert_eq!(infer("sum 1 2"), "decimal-number");
+    assert_eq!(infer("list 1 2.5"), "list of number");
+    assert_eq!(infer("dict a=1 b=null"), "dict of nullable integer-number");
+    assert_eq!(infer("frobnicate 1"), "anything");
+    assert_eq!(infer("1..5"), "range of integer-number");
+    
+    // Pipes pass the type of their items on as `$`.
+    assert_eq!(infer("list bob ann | heal $"), "list of char-string");
+    assert_eq!(infer("0..3 | $"), "list of integer-number");
+    assert_eq!(infer("0..3 |? $ |?! $"), "nullable integer-number");
+    assert_eq!(infer("0..3 |! 0 (sum $acc $)"), "number");
+    assert_eq!(infer("list bob |> (list)"), "list of anything");
+    assert_eq!(infer("list bob |> (list ann)"), "list of char-string");
+    assert_eq!(infer("player | $.weight"), "list of decimal-number");
+    
+    // Fields and indices, via the schemas of host objects.
+    assert_eq!(infer("player => $.name"), "char-string");
+    assert_eq!(infer("player => $.[0].weight"), "decimal-number");
+    assert_eq!(infer("player => $.mana"), "anything");
+    assert_eq!(infer("list 1 2 => $.[0]"), "integer-number");
+    
+    // Unwrapping, statements and variables.
+    assert_eq!(infer("(heal bob)?"), "nullable char-string");
+    assert_eq!(infer("dict a=null -> $x; $x.a?!"), "nil");
+    assert_eq!(infer("player -> $p; $p.health"), "integer-number");
+    assert_eq!(infer("heal bob +> $l; $l"), "list of char-string");
+    assert_eq!(infer("sum 1; heal $"), "char-string");
+    assert_eq!(infer("sum 1 && heal bob"), "char-string");
+    assert_eq!(infer("list 1 null => (= $.[0] && (heal bob))"), "nullable char-string");
+    assert_eq!(infer("= (sum 1) || 2"), "number");
+    
+    // Every read of a variable is typed on its own.
+    let block = crate::parser::parse_script("1 -> $x; $x; heal bob -> $x; $x").expect("input should parse");
+    let types = crate::registry::infer(&block, &registry);
+    let crate::parser::Expression::Sequence(statements) = block.get(block.entry().unwrap()) else { panic!("expected a sequence") };
+    assert_eq!(types.get(statements[1]).to_string(), "integer-number");
+    assert_eq!(types.get(statements[3]).to_string(), "char-string");
+    
+    // Every reachable expression is typed.
+    let mut block = crate::parser::parse_script("heal (player).name amount=(sum 1)").expect("input should parse");
+    block.compact();
+    let types = crate::registry::infer(&block, &registry);
+    for (br, _) in block.iter() {
+        assert!(types.is_reachable(br), "{br:?}");
+    }
+}
@@ -0,0 +1,216 @@
+//! Static types, as inferred for expressions ahead of evaluation.
+
+use std::fmt::Display;
+
+use super::*;
+use crate::lexer::Literal;
+
+/// The statically inferred type of an expression.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// Could be anything; nothing is known.
+    #[default]
+    Any,
+    /// Always `null`.
+    Nil,
+    /// A boolean.
+    Bool,
+    /// An integer number.
+    Int,
+    /// A decimal number.
+    Dec,
+    /// Either an integer or a decimal number.
+    Number,
+    /// A unique identifier.
+    Uid,
+    /// A string.
+    Str,
+    /// A byte-string.
+    Byt,
+    /// An object reference (`@…`).
+    ObjRef,
+    /// A list of items of the given type.
+    List(Box<Type>),
+    /// A dict with values of the given type.
+    Dict(Box<Type>),
+    /// A range with bounds of the given type.
+    Range(Box<Type>),
+    /// A host object, of the given type if known; see [`TypeSchema`].
+    Object(Option<CompactString>),
+    /// A closure.
+    Closure,
+    /// Either `null` or the given type.
+    Nullable(Box<Type>),
+}
+
+impl Type {
+    /// Returns the type of the given literal; `Any` for references.
+    pub fn of_literal(literal: &Literal) -> Self {
+        match literal {
+            Literal::Nil => Type::Nil,
+            Literal::Bool(_) => Type::Bool,
+            Literal::Int(_) => Type::Int,
+            Literal::Dec(_) => Type::Dec,
+            Literal::Uid(_) => Type::Uid,
+            Literal::Str(_) => Type::Str,
+            Literal::Byt(_) => Type::Byt,
+            Literal::ObjIdx(_) | Literal::ObjUid(_) | Literal::ObjKey(_) => Type::ObjRef,
+            Literal::RefRes | Literal::RefCtx | Literal::RefVar(_) => Type::Any,
+        }
+    }
+    
+    /// Creates the type of a list of the given items.
+    pub fn list(item: impl Into<Type>) -> Self {
+        Type::List(Box::new(item.into()))
+    }
+    
+    /// Creates the type of a dict of the given values.
+    pub fn dict(value: impl Into<Type>) -> Self {
+        Type::Dict(Box::new(value.into()))
+    }
+    
+    /// Creates the type of a host object of the given type.
+    pub fn object(name: impl Into<CompactString>) -> Self {
+        Type::Object(Some(name.into()))
+    }
+    
+    /// Makes the type nullable, unless it already is.
+    #[must_use]
+    pub fn nullable(self) -> Self {
+        match self {
+            Type::Any | Type::Nil | Type::Nullable(_) => self,
+            other => Type::Nullable(Box::new(other)),
+        }
+    }
+    
+    /// Removes the nullability of the type, if any.
+    #[must_use]
+    pub fn non_null(self) -> Self {
+        match self {
+            Type::Nullable(inner) => *inner,
+            other => other,
+        }
+    }
+    
+    /// Returns the type that covers both types, for expressions that may yield either.
+    #[must_use]
+    pub fn join(self, other: Type) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Type::Any, _) | (_, Type::Any) => Type::Any,
+            (Type::Nil, other) | (other, Type::Nil) => other.nullable(),
+            (Type::Nullable(a), b) | (b, Type::Nullable(a)) => a.join(b).nullable(),
+            (Type::Int | Type::Dec | Type::Number, Type::Int | Type::Dec | Type::Number) => Type::Number,
+            (Type::List(a), Type::List(b)) => Type::List(Box::new(a.join(*b))),
+            (Type::Dict(a), Type::Dict(b)) => Type::Dict(Box::new(a.join(*b))),
+            (Type::Range(a), Type::Range(b)) => Type::Range(Box::new(a.join(*b))),
+            (Type::Object(_), Type::Object(_)) => Type::Object(None),
+            _ => Type::Any,
+        }
+    }
+    
+    /// Returns the kind of the values of this type; `Any` if there are several.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Type::Any | Type::Nullable(_) => Kind::Any,
+            Type::Nil => Kind::Nil,
+            Type::Bool => Kind::Bool,
+            Type::Int => Kind::Int,
+            Type::Dec => Kind::Dec,
+            Type::Number => Kind::Number,
+            Type::Uid => Kind::Uid,
+            Type::Str => Kind::Str,
+            Type::Byt => Kind::Byt,
+            Type::ObjRef => Kind::ObjRef,
+            Type::List(_) => Kind::List,
+            Type::Dict(_) => Kind::Dict,
+            Type::Range(_) => Kind::Range,
+            Type::Object(_) => Kind::Object,
+            Type::Closure => Kind::Closure,
+        }
+    }
+}
+
+impl From<Kind> for Type {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Any => Type::Any,
+            Kind::Nil => Type::Nil,
+            Kind::Bool => Type::Bool,
+            Kind::Int => Type::Int,
+            Kind::Dec => Type::Dec,
+            Kind::Number => Type::Number,
+            Kind::Uid => Type::Uid,
+            Kind::Str => Type::Str,
+            Kind::Byt => Type::Byt,
+            Kind::ObjRef => Type::ObjRef,
+            Kind::List => Type::list(Type::Any),
+            Kind::Dict => Type::dict(Type::Any),
+            Kind::Range => Type::Range(Box::new(Type::Any)),
+            Kind::Object => Type::Object(None),
+            Kind::Closure => Type::Closure,
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::List(item) => write!(f, "list of {item}"),
+            Type::Dict(value) => write!(f, "dict of {value}"),
+            Type::Range(bound) => write!(f, "range of {bound}"),
+            Type::Object(Some(name)) => write!(f, "{name}"),
+            Type::Nullable(inner) => write!(f, "nullable {inner}"),
+            other => write!(f, "{}", other.kind().get_type_str()),
+        }
+    }
+}
+
+/// The static description of a type of host object, for inferring the types of its fields, elements and items.
+/// 
+/// The name is the same as returned by [`HostObject::type_name`](crate::values::HostObject::type_name).
+#[derive(Debug, Clone, Default)]
+pub struct TypeSchema {
+    /// The name of the type.
+    pub name: CompactString,
+    
+    /// The types of the fields.
+    pub fields: FxHashMap<CompactString, Type>,
+    
+    /// The type of the elements accessed by index.
+    pub index: Type,
+    
+    /// The type of the items when piped.
+    pub items: Type,
+}
+
+impl TypeSchema {
+    /// Creates a new schema without any fields.
+    pub fn new(name: impl Into<CompactString>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+    
+    /// Adds a field of the given type.
+    #[must_use]
+    pub fn field(mut self, name: impl Into<CompactString>, ty: impl Into<Type>) -> Self {
+        self.fields.insert(name.into(), ty.into());
+        self
+    }
+    
+    /// Sets the type of the elements accessed by index.
+    #[must_use]
+    pub fn index(mut self, ty: impl Into<Type>) -> Self {
+        self.index = ty.into();
+        self
+    }
+    
+    /// Sets the type of the items when piped.
+    #[must_use]
+    pub fn items(mut self, ty: impl Into<Type>) -> Self {
+        self.items = ty.into();
+        self
+    }
+}
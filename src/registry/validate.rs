@@ -0,0 +1,344 @@
+//! Static validation of blocks against the signatures of registered commands.
+
+use super::*;
+use crate::lexer::{Literal, Span};
+use crate::parser::{Arrow, Block, BlockRef, Definition, Expression, FnCall, PipeSeg};
+
+/// A problem found by [`validate()`], which would (most likely) make evaluation fail.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A command is neither registered nor defined within the block; maybe with a similar name.
+    #[error("Unknown command '{1}'")]
+    UnknownCommand(Span, CompactString, Option<CompactString>),
+    
+    /// A required argument was not given.
+    #[error("Command '{1}' is missing the argument '{2}'")]
+    Missing(Span, CompactString, CompactString),
+    
+    /// More positional arguments were given than the command accepts.
+    #[error("Command '{1}' takes at most {2} positional arguments")]
+    Extra(Span, CompactString, usize),
+    
+    /// A named argument was given that the command does not know.
+    #[error("Command '{1}' has no parameter named '{2}'")]
+    Unknown(Span, CompactString, CompactString),
+    
+    /// A named argument was given more than once.
+    #[error("Argument '{2}' of command '{1}' is given more than once")]
+    Duplicate(Span, CompactString, CompactString),
+    
+    /// A flag (`+name`/`-name`) was given for a parameter that does not take booleans.
+    #[error("Argument '{2}' of command '{1}' is not a flag")]
+    NotAFlag(Span, CompactString, CompactString),
+    
+    /// An argument is of the wrong kind, as proven by its literal.
+    #[error("Argument '{2}' of command '{1}' expects a {3}, but got a {4}")]
+    Mismatch(Span, CompactString, CompactString, &'static str, &'static str),
+    
+    /// The result reference (`$`) was used where no result is passed on.
+    #[error("There is no result to refer to via `$`")]
+    StrayResult(Span),
+}
+
+impl ValidationError {
+    /// Returns the span of the input that caused the error.
+    pub fn span(&self) -> Span {
+        match self {
+            ValidationError::UnknownCommand(span, _, _) => *span,
+            ValidationError::Missing(span, _, _) => *span,
+            ValidationError::Extra(span, _, _) => *span,
+            ValidationError::Unknown(span, _, _) => *span,
+            ValidationError::Duplicate(span, _, _) => *span,
+            ValidationError::NotAFlag(span, _, _) => *span,
+            ValidationError::Mismatch(span, _, _, _, _) => *span,
+            ValidationError::StrayResult(span) => *span,
+        }
+    }
+}
+
+/// Validates every command invocation in the block against the registry, without evaluating anything.
+/// 
+/// Commands defined within the block (via `def`/`alias`) and namespaces imported via `use` are taken into account,
+/// from the point at which they are defined or imported on.
+/// The result reference (`$`) may only be used within pipes, after `&&` and arrows, in later statements and in quoted code.
+/// 
+/// Returns every problem found, in the order they appear; empty if there are none.
+pub fn validate(block: &Block, registry: &CommandRegistry) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        registry,
+        defined: FxHashMap::default(),
+        imports: Vec::new(),
+        errors: Vec::new(),
+    };
+    validator.block(block, false);
+    validator.errors
+}
+
+/// The state of a validation.
+struct Validator<'r> {
+    /// The registry to validate against.
+    registry: &'r CommandRegistry,
+    /// The signatures of the commands defined so far within the block.
+    defined: FxHashMap<CompactString, Signature>,
+    /// The namespaces and commands imported so far.
+    imports: Vec<CommandPath>,
+    /// The problems found so far.
+    errors: Vec<ValidationError>,
+}
+
+impl Validator<'_> {
+    /// Collects the signature of a command defined within the block, as evaluating its definition would.
+    fn define(&mut self, def: &Definition) {
+        let mut signature = Signature::new(def.name.to_string());
+        for param in &def.positional {
+            signature = signature.positional(param.clone(), Kind::Any);
+        }
+        for (param, default) in &def.named {
+            match default {
+                // The default is only known once evaluated.
+                Some(_) => signature = signature.named(param.clone(), Kind::Any, Value::Nil),
+                None => signature.named.push(Param { name: param.clone(), kind: Kind::Any, default: None, flag: false }),
+            }
+        }
+        self.defined.insert(signature.name.clone(), signature);
+    }
+    
+    /// Validates a block, starting at its entry.
+    fn block(&mut self, block: &Block, bound: bool) {
+        if let Some(entry) = block.entry() {
+            self.expr(block, entry, bound);
+        }
+    }
+    
+    /// Validates an expression; `bound` tells if a result is passed on to it as `$`.
+    fn expr(&mut self, block: &Block, br: BlockRef, bound: bool) {
+        match block.get(br) {
+            Expression::Value(Literal::RefRes) if !bound => {
+                self.errors.push(ValidationError::StrayResult(block.span(br)));
+            },
+            Expression::FnCall(call) => self.call(block, br, call, None, bound),
+            Expression::Method(target, call) => {
+                self.expr(block, *target, bound);
+                match block.get(*call) {
+                    Expression::FnCall(call) => self.call(block, br, call, Some(*target), bound),
+                    _ => self.expr(block, *call, bound),
+                }
+            },
+            Expression::Pipe(pipe) => {
+                self.expr(block, pipe.source, bound);
+                for seg in &pipe.stages {
+                    match seg {
+                        PipeSeg::Collect { collector } => self.expr(block, *collector, true),
+                        PipeSeg::Mapping { mapper } => self.expr(block, *mapper, true),
+                        PipeSeg::Folding { initial, reducer } => {
+                            self.expr(block, *initial, bound);
+                            self.expr(block, *reducer, true);
+                        },
+                        PipeSeg::Exclude { predicate } => self.expr(block, *predicate, true),
+                        PipeSeg::Finding { predicate } => self.expr(block, *predicate, true),
+                    }
+                }
+            },
+            Expression::Arrow(arrow, left, right) => {
+                self.expr(block, *left, bound);
+                match arrow {
+                    Arrow::Assign | Arrow::Append => self.expr(block, *right, bound),
+                    Arrow::Bind | Arrow::Defer | Arrow::Grid => self.expr(block, *right, true),
+                }
+            },
+            Expression::Sequence(statements) => {
+                for (i, statement) in statements.iter().enumerate() {
+                    self.expr(block, *statement, bound || i > 0);
+                }
+            },
+            // Closures are invoked with a result; defined commands with that of the caller.
+            Expression::Code(code) => self.block(&code.body, true),
+            Expression::Def(def) => {
+                for (_, default) in &def.named {
+                    if let Some(default) = default {
+                        self.expr(block, *default, bound);
+                    }
+                }
+                // Defined before its body, which may call itself.
+                self.define(def);
+                self.block(&def.code.body, true);
+            },
+            expr => for child in expr.children() {
+                self.expr(block, child, bound);
+            },
+        }
+    }
+    
+    /// Validates an invocation, optionally with a receiver as first positional argument.
+    fn call(&mut self, block: &Block, br: BlockRef, call: &FnCall, receiver: Option<BlockRef>, bound: bool) {
+        // Mirrors `Interpreter::eval_intrinsic`.
+        let intrinsic = match call.name.segments() {
+            [name] if receiver.is_none() => name.as_str(),
+            _ => "",
+        };
+        match intrinsic {
+            "if-then" if call.pos_args.len() == 2 => {
+                self.expr(block, call.pos_args[0], bound);
+                self.expr(block, call.pos_args[1], true);
+                return
+            },
+            "use" if call.nom_args.is_empty() => {
+                for arg in &call.pos_args {
+                    if let Expression::Value(Literal::Str(path)) = block.get(*arg) {
+                        self.imports.push(path.clone().into());
+                    }
+                }
+                return
+            },
+            "list" | "dict" | "if-else" => {
+                for arg in block.children(br) {
+                    self.expr(block, arg, bound);
+                }
+                return
+            },
+            _ => (),
+        }
+        
+        for arg in call.pos_args.iter().chain(call.nom_args.values()) {
+            self.expr(block, *arg, bound);
+        }
+        
+        // Closures are invoked without the host, and thus have no signature.
+        if call.name == "call" {
+            return
+        }
+        
+        let name = call.name.to_string();
+        let signature = match self.defined.get(name.as_str()) {
+            Some(signature) => signature,
+            None => match self.registry.resolve(&name, &self.imports) {
+                Some(id) => &self.registry.get(id).signature,
+                None => {
+                    let suggestion = self.suggest(&name);
+                    self.errors.push(ValidationError::UnknownCommand(block.span(br), name.into(), suggestion));
+                    return
+                },
+            },
+        };
+        
+        let errors = check_arguments(block, br, call, receiver, signature);
+        self.errors.extend(errors);
+    }
+    
+    /// Returns the name of the known command most similar to the given one, if any is similar enough.
+    fn suggest(&self, name: &str) -> Option<CompactString> {
+        let max = name.chars().count().div_ceil(3);
+        self.registry.names()
+            .chain(self.defined.keys().map(|k| k.as_str()))
+            .map(|candidate| {
+                let short = candidate.rsplit(CommandPath::SEPARATOR).next().unwrap_or(candidate);
+                (edit_distance(name, candidate).min(edit_distance(name, short)), candidate)
+            })
+            .filter(|(distance, _)| *distance <= max)
+            .min()
+            .map(|(_, candidate)| candidate.into())
+    }
+}
+
+/// Checks the arguments of an invocation against the signature of the invoked command.
+fn check_arguments(
+    block: &Block,
+    br: BlockRef,
+    call: &FnCall,
+    receiver: Option<BlockRef>,
+    signature: &Signature
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let name = &signature.name;
+    let pos_args: Vec<BlockRef> = receiver.into_iter().chain(call.pos_args.iter().copied()).collect();
+    
+    for (i, param) in signature.positional.iter().enumerate() {
+        match pos_args.get(i) {
+            Some(arg) => errors.extend(check_kind(block, *arg, name, param)),
+            None if param.default.is_none() => {
+                errors.push(ValidationError::Missing(block.span(br), name.clone(), param.name.clone()));
+            },
+            None => (),
+        }
+    }
+    
+    if let Some(extra) = pos_args.get(signature.positional.len()..) {
+        match &signature.variadic {
+            Some(param) => for arg in extra {
+                errors.extend(check_kind(block, *arg, name, param));
+            },
+            None => if let Some(arg) = extra.first() {
+                errors.push(ValidationError::Extra(block.span(*arg), name.clone(), signature.positional.len()));
+            },
+        }
+    }
+    
+    for param in &signature.named {
+        if param.default.is_none() && !call.nom_args.contains_key(&param.name) {
+            errors.push(ValidationError::Missing(block.span(br), name.clone(), param.name.clone()));
+        }
+    }
+    
+    // Named arguments are unordered; sort them, to report in a stable order.
+    let mut nom_args: Vec<_> = call.nom_args.iter().collect();
+    nom_args.sort();
+    for (key, arg) in nom_args {
+        let span = block.span(*arg);
+        if call.is_duplicate(key) {
+            errors.push(ValidationError::Duplicate(span, name.clone(), key.clone()));
+        }
+        
+        match signature.get_named(key) {
+            None => errors.push(ValidationError::Unknown(span, name.clone(), key.clone())),
+            Some(param) if call.is_flag(key) && !param.flag && !param.kind.accepts_kind(Kind::Bool) => {
+                errors.push(ValidationError::NotAFlag(span, name.clone(), key.clone()));
+            },
+            Some(param) => errors.extend(check_kind(block, *arg, name, param)),
+        }
+    }
+    
+    errors
+}
+
+/// Checks that the parameter accepts the argument, if the kind of the argument is known without evaluating it.
+fn check_kind(block: &Block, arg: BlockRef, name: &CompactString, param: &Param) -> Option<ValidationError> {
+    let kind = match block.get(arg) {
+        Expression::Value(Literal::RefRes | Literal::RefCtx | Literal::RefVar(_)) => return None,
+        Expression::Value(literal) => Kind::of(&Value::from(literal)),
+        Expression::Range(_, _, _) => Kind::Range,
+        Expression::Code(_) => Kind::Closure,
+        Expression::FnCall(call) => match call.name.segments() {
+            [name] if name == "list" => Kind::List,
+            [name] if name == "dict" => Kind::Dict,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    
+    if param.kind.accepts_kind(kind) {
+        return None
+    }
+    Some(ValidationError::Mismatch(block.span(arg), name.clone(), param.name.clone(), param.kind.get_type_str(), kind.get_type_str()))
+}
+
+/// Returns the number of single-character edits (including swaps of adjacent characters) that turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    
+    // Optimal string alignment, keeping the last two rows.
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut last, row);
+    }
+    last[b.len()]
+}
@@ -0,0 +1,366 @@
+//! A compact, NaN-tagged container for runtime values.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use super::*;
+
+/// A NaN-tagged container for values at runtime, exactly 8 bytes in size.
+/// 
+/// Decimal numbers are stored as-is, with every NaN being canonicalized to a single quiet NaN.
+/// The remaining NaN-space is used to store `null`, booleans and 32-bit integers inline;
+/// every other value is boxed, with the pointer stored in the lower 48 bits.
+/// 
+/// Should a pointer not fit (as with 57-bit address spaces), the value is stored
+/// in a thread-local table instead, and only its slot is kept in the container.
+pub struct ValContainer {
+    bits: u64,
+    /// The container (maybe) owns a boxed [`Value`], which is neither `Send` nor `Sync`.
+    marker: PhantomData<Box<Value>>,
+}
+
+/// The bits of the canonical quiet NaN, used for all NaN decimals.
+const CANONICAL_NAN: u64 = 0x7FF8_0000_0000_0000;
+
+/// The bits shared by all tagged (non-decimal) values.
+const TAG_MASK: u64 = 0xFFFF_0000_0000_0000;
+
+const TAG_NIL: u64 = 0xFFF9_0000_0000_0000;
+const TAG_BOOL: u64 = 0xFFFA_0000_0000_0000;
+const TAG_INT: u64 = 0xFFFB_0000_0000_0000;
+const TAG_BOX: u64 = 0xFFFC_0000_0000_0000;
+const TAG_SLOT: u64 = 0xFFFD_0000_0000_0000;
+
+/// The bits available to the payload of a tagged value.
+const PAYLOAD_MASK: u64 = !TAG_MASK;
+
+thread_local! {
+    /// The values whose pointer did not fit into the payload of a container.
+    static SLOTS: RefCell<Slots> = RefCell::default();
+}
+
+/// A table of values, reusing the slots of removed ones.
+#[derive(Default)]
+struct Slots {
+    values: Vec<Option<Value>>,
+    free: Vec<usize>,
+}
+
+impl Slots {
+    fn insert(&mut self, value: Value) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.values[slot] = Some(value);
+                slot
+            },
+            None => {
+                self.values.push(Some(value));
+                self.values.len() - 1
+            },
+        }
+    }
+    
+    fn remove(&mut self, slot: usize) -> Value {
+        self.free.push(slot);
+        self.values[slot].take().unwrap_or_default()
+    }
+}
+
+impl ValContainer {
+    const fn from_bits(bits: u64) -> Self {
+        Self { bits, marker: PhantomData }
+    }
+    
+    /// Returns a container holding `null`.
+    pub const fn nil() -> Self {
+        Self::from_bits(TAG_NIL)
+    }
+    
+    /// Returns a container holding the given boolean.
+    pub const fn bool(v: bool) -> Self {
+        Self::from_bits(TAG_BOOL | v as u64)
+    }
+    
+    /// Returns a container holding the given integer.
+    pub const fn int(v: i32) -> Self {
+        Self::from_bits(TAG_INT | v as u32 as u64)
+    }
+    
+    /// Returns a container holding the given decimal.
+    pub fn dec(v: f64) -> Self {
+        if v.is_nan() {
+            Self::from_bits(CANONICAL_NAN)
+        } else {
+            Self::from_bits(v.to_bits())
+        }
+    }
+    
+    /// Packs the given value into a container, boxing it if it cannot be stored inline.
+    pub fn new(value: Value) -> Self {
+        match value {
+            Value::Nil => Self::nil(),
+            Value::Bool(v) => Self::bool(v),
+            Value::Dec(v) => Self::dec(v),
+            Value::Int(v) => match i32::try_from(v) {
+                Ok(v) => Self::int(v),
+                Err(_) => Self::boxed(Value::Int(v)),
+            },
+            value => Self::boxed(value),
+        }
+    }
+    
+    fn boxed(value: Value) -> Self {
+        let ptr = Box::into_raw(Box::new(value));
+        if ptr as u64 & TAG_MASK == 0 {
+            return Self::from_bits(TAG_BOX | ptr as u64);
+        }
+        
+        // SAFETY: The pointer just came from `Box::into_raw`, and is not used afterwards.
+        Self::slotted(*unsafe { Box::from_raw(ptr) })
+    }
+    
+    pub(super) fn slotted(value: Value) -> Self {
+        let slot = SLOTS.with(|slots| slots.borrow_mut().insert(value));
+        Self::from_bits(TAG_SLOT | slot as u64)
+    }
+    
+    fn tag(&self) -> u64 {
+        self.bits & TAG_MASK
+    }
+    
+    fn slot(&self) -> Option<usize> {
+        (self.tag() == TAG_SLOT).then_some((self.bits & PAYLOAD_MASK) as usize)
+    }
+    
+    fn as_boxed(&self) -> Option<&Value> {
+        if self.tag() == TAG_BOX {
+            // SAFETY: Boxed containers always hold a pointer from `Box::into_raw`, which is only freed on drop.
+            Some(unsafe { &*((self.bits & PAYLOAD_MASK) as *const Value) })
+        } else {
+            None
+        }
+    }
+    
+    /// Is the container holding `null`?
+    pub fn is_nil(&self) -> bool {
+        self.bits == TAG_NIL
+    }
+    
+    /// Returns the boolean held by the container, if any.
+    pub fn as_bool(&self) -> Option<bool> {
+        (self.tag() == TAG_BOOL).then_some(self.bits & 1 == 1)
+    }
+    
+    /// Returns the integer held inline by the container, if any.
+    pub fn as_int(&self) -> Option<i32> {
+        (self.tag() == TAG_INT).then_some(self.bits as u32 as i32)
+    }
+    
+    /// Returns the decimal held by the container, if any.
+    pub fn as_dec(&self) -> Option<f64> {
+        match self.tag() {
+            TAG_NIL | TAG_BOOL | TAG_INT | TAG_BOX | TAG_SLOT => None,
+            _ => Some(f64::from_bits(self.bits)),
+        }
+    }
+    
+    /// Returns the value held by the container; borrowed if boxed, owned if inline.
+    pub fn get(&self) -> Cow<'_, Value> {
+        if let Some(value) = self.as_boxed() {
+            return Cow::Borrowed(value);
+        }
+        
+        if let Some(slot) = self.slot() {
+            return Cow::Owned(SLOTS.with(|slots| slots.borrow().values[slot].clone().unwrap_or_default()));
+        }
+        
+        Cow::Owned(match self.tag() {
+            TAG_NIL => Value::Nil,
+            TAG_BOOL => Value::Bool(self.bits & 1 == 1),
+            TAG_INT => Value::Int((self.bits as u32 as i32).into()),
+            _ => Value::Dec(f64::from_bits(self.bits)),
+        })
+    }
+    
+    /// Unpacks the value held by the container.
+    pub fn into_value(self) -> Value {
+        if self.tag() == TAG_BOX {
+            let this = std::mem::ManuallyDrop::new(self);
+            // SAFETY: The pointer came from `Box::into_raw`, and `this` is never dropped.
+            return *unsafe { Box::from_raw((this.bits & PAYLOAD_MASK) as *mut Value) };
+        }
+        if let Some(slot) = self.slot() {
+            std::mem::forget(self);
+            return SLOTS.with(|slots| slots.borrow_mut().remove(slot));
+        }
+        self.get().into_owned()
+    }
+}
+
+impl Default for ValContainer {
+    fn default() -> Self {
+        Self::nil()
+    }
+}
+
+impl Clone for ValContainer {
+    fn clone(&self) -> Self {
+        match self.as_boxed() {
+            Some(value) => Self::boxed(value.clone()),
+            None if self.slot().is_some() => Self::slotted(self.get().into_owned()),
+            None => Self::from_bits(self.bits),
+        }
+    }
+}
+
+impl Drop for ValContainer {
+    fn drop(&mut self) {
+        if self.tag() == TAG_BOX {
+            // SAFETY: The pointer came from `Box::into_raw`, and is dropped exactly once.
+            drop(unsafe { Box::from_raw((self.bits & PAYLOAD_MASK) as *mut Value) });
+        }
+        if let Some(slot) = self.slot() {
+            // The table may already be gone while other thread-locals are destroyed.
+            let _ = SLOTS.try_with(|slots| slots.borrow_mut().remove(slot));
+        }
+    }
+}
+
+impl PartialEq for ValContainer {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl std::fmt::Debug for ValContainer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.get(), f)
+    }
+}
+
+impl From<Value> for ValContainer {
+    fn from(value: Value) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<ValContainer> for Value {
+    fn from(val: ValContainer) -> Self {
+        val.into_value()
+    }
+}
+
+impl From<&Literal> for ValContainer {
+    fn from(literal: &Literal) -> Self {
+        Self::new(literal.into())
+    }
+}
+
+impl From<()> for ValContainer {
+    fn from(_: ()) -> Self {
+        Self::nil()
+    }
+}
+
+impl From<bool> for ValContainer {
+    fn from(v: bool) -> Self {
+        Self::bool(v)
+    }
+}
+
+impl From<i32> for ValContainer {
+    fn from(v: i32) -> Self {
+        Self::int(v)
+    }
+}
+
+impl From<f64> for ValContainer {
+    fn from(v: f64) -> Self {
+        Self::dec(v)
+    }
+}
+
+impl From<&str> for ValContainer {
+    fn from(str: &str) -> Self {
+        Self::boxed(Value::Str(str.into()))
+    }
+}
+
+impl From<CompactString> for ValContainer {
+    fn from(str: CompactString) -> Self {
+        Self::boxed(Value::Str(str))
+    }
+}
+
+impl From<Vec<ValContainer>> for ValContainer {
+    fn from(list: Vec<ValContainer>) -> Self {
+        Self::boxed(Value::List(list.into_iter().map(ValContainer::into_value).collect()))
+    }
+}
+
+impl TryFrom<&ValContainer> for i32 {
+    type Error = &'static str;
+    
+    fn try_from(val: &ValContainer) -> Result<Self, Self::Error> {
+        match &*val.get() {
+            Value::Dec(d) => Ok(d.floor() as Self),
+            Value::Int(i) => Self::try_from(*i).map_err(|_| "unable to cast large integer to i32"),
+            Value::Nil => Err("unable to cast nothing to i32"),
+            Value::Bool(_) => Err("unable to cast bool to i32"),
+            Value::Str(_) => Err("unable to cast string to i32"),
+            Value::Byt(_) => Err("unable to cast bytes to i32"),
+            Value::List(_) => Err("unable to cast list to i32"),
+            Value::Dict(_) => Err("unable to cast map to i32"),
+            _ => Err("unable to cast value to i32"),
+        }
+    }
+}
+
+impl TryFrom<&ValContainer> for f64 {
+    type Error = &'static str;
+    
+    fn try_from(val: &ValContainer) -> Result<Self, Self::Error> {
+        match &*val.get() {
+            Value::Dec(d) => Ok(*d),
+            Value::Int(i) => Ok(*i as Self),
+            Value::Nil => Err("unable to cast nothing to f64"),
+            Value::Bool(_) => Err("unable to cast bool to f64"),
+            Value::Str(_) => Err("unable to cast string to f64"),
+            Value::Byt(_) => Err("unable to cast bytes to f64"),
+            Value::List(_) => Err("unable to cast list to f64"),
+            Value::Dict(_) => Err("unable to cast map to f64"),
+            _ => Err("unable to cast value to f64"),
+        }
+    }
+}
+
+impl From<&ValContainer> for String {
+    fn from(val: &ValContainer) -> Self {
+        format!("{:?}", &val)
+    }
+}
+
+impl TryFrom<&ValContainer> for Vec<ValContainer> {
+    type Error = &'static str;
+    
+    fn try_from(val: &ValContainer) -> Result<Self, Self::Error> {
+        use std::iter::once;
+        
+        match &*val.get() {
+            Value::Nil => Ok(Default::default()),
+            Value::Byt(bytes) => Ok(bytes.data.iter().map(|b| ValContainer::int(*b as i32)).collect()),
+            Value::List(list) => Ok(list.iter().cloned().map(ValContainer::new).collect()),
+            Value::Dict(map) => Ok(map
+                .iter()
+                .flat_map(|(k,v)| once(ValContainer::from(k.clone())).chain(once(ValContainer::new(v.clone()))))
+                .collect()
+            ),
+            Value::Dec(_) => Err("unable to cast decimal to list"),
+            Value::Int(_) => Err("unable to cast integer to list"),
+            Value::Bool(_) => Err("unable to cast bool to list"),
+            Value::Str(_) => Err("unable to cast string to list"),
+            _ => Err("unable to cast value to list"),
+        }
+    }
+}
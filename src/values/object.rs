@@ -0,0 +1,36 @@
+//! Objects provided by the host, opaque to the language.
+
+use std::any::Any;
+use std::fmt::Debug;
+
+use super::*;
+
+/// An object provided by the host, exposed to scripts as a [`Value::Object`].
+/// 
+/// Objects are shared by reference and compared by identity.
+pub trait HostObject: Debug {
+    /// Returns the name of the objects type, as shown in errors.
+    fn type_name(&self) -> &'static str;
+    
+    /// Returns the value of the field with the given name, if there is one.
+    fn field(&self, name: &str) -> Option<Value> {
+        let _ = name;
+        None
+    }
+    
+    /// Returns the element at the given index, if there is one.
+    fn index(&self, index: &Value) -> Option<Value> {
+        let _ = index;
+        None
+    }
+    
+    /// Returns an iterator over the items of the object, if it is iterable.
+    /// 
+    /// Pipes pull items from the iterator one at a time.
+    fn iter(&self) -> Option<Box<dyn Iterator<Item = Value>>> {
+        None
+    }
+    
+    /// Returns the object as [`Any`], for downcasting by the host.
+    fn as_any(&self) -> &dyn Any;
+}
@@ -0,0 +1,88 @@
+//! Tests to ensure values are packed correctly.
+
+use super::*;
+
+#[test]
+fn container_roundtrip() {
+    let values = [
+        Value::Nil,
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Int(-7),
+        Value::Int(i64::MAX),
+        Value::Dec(0.5),
+        Value::Dec(f64::NEG_INFINITY),
+        Value::Str("hello".into()),
+        Value::ObjKey("player".into()),
+        Value::List(vec![1.into(), "two".into(), 3.0.into()]),
+    ];
+    
+    for value in values {
+        let container = ValContainer::new(value.clone());
+        assert_eq!(*container.get(), value);
+        assert_eq!(container.clone().into_value(), value);
+    }
+    
+    assert!(ValContainer::new(Value::Nil).is_nil());
+    assert_eq!(ValContainer::int(-1).as_int(), Some(-1));
+    assert_eq!(ValContainer::new(Value::Int(1 << 40)).as_int(), None);
+    assert_eq!(ValContainer::dec(-f64::NAN).as_dec().map(f64::is_nan), Some(true));
+    assert_eq!(ValContainer::bool(true).as_dec(), None);
+}
+
+#[test]
+fn container_slots() {
+    // Pointers that don't fit into 48 bits end up in a slot instead.
+    let value = Value::Str("far away".into());
+    let container = ValContainer::slotted(value.clone());
+    assert_eq!(*container.get(), value);
+    assert_eq!(container.as_dec(), None);
+    
+    let cloned = container.clone();
+    drop(container);
+    assert_eq!(*cloned.get(), value);
+    assert_eq!(cloned.into_value(), value);
+    
+    let reused = ValContainer::slotted(Value::Int(1 << 40));
+    assert_eq!(reused, ValContainer::new(Value::Int(1 << 40)));
+}
+
+#[test]
+fn container_conversions() {
+    assert_eq!(i32::try_from(&ValContainer::dec(2.5)), Ok(2));
+    assert_eq!(f64::try_from(&ValContainer::int(3)), Ok(3.0));
+    assert!(i32::try_from(&ValContainer::from("3")).is_err());
+    
+    let list = ValContainer::from(vec![ValContainer::int(1), ValContainer::bool(false)]);
+    assert_eq!(Vec::<ValContainer>::try_from(&list), Ok(vec![ValContainer::int(1), ValContainer::bool(false)]));
+    assert_eq!(Vec::<ValContainer>::try_from(&ValContainer::nil()), Ok(vec![]));
+    assert_eq!(String::from(&list), "[1i false]");
+}
+
+#[derive(Debug)]
+struct Player;
+
+impl HostObject for Player {
+    fn type_name(&self) -> &'static str {
+        "player"
+    }
+    
+    fn field(&self, name: &str) -> Option<Value> {
+        (name == "health").then_some(Value::Int(100))
+    }
+    
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[test]
+fn host_objects() {
+    let player: Rc<dyn HostObject> = Rc::new(Player);
+    let value = Value::from(player.clone());
+    
+    assert_eq!(value.get_type_str(), "player");
+    assert_eq!(value, Value::Object(player));
+    assert_ne!(value, Value::Object(Rc::new(Player)));
+    assert_eq!(*ValContainer::new(value.clone()).get(), value);
+}
@@ -0,0 +1,379 @@
+//! A compact bytecode for [`Block`]s, and a stack-based virtual machine to run it.
+//! 
+//! Compiling a block against a [`CommandRegistry`] resolves every command to its [`CommandId`],
+//! and binds the arguments of every call to the parameters of the command ahead of time;
+//! running the resulting [`Program`] thus needs neither name lookups nor hashing of named arguments.
+//! 
+//! Expressions without a bytecode of their own (pipes, quoted code, definitions, …)
+//! are evaluated by the tree-walking [`Interpreter`] instead.
+
+use smartstring::alias::CompactString;
+
+use crate::eval::{Arguments, Context, EvalError, Interpreter, Value, into_grid};
+use crate::parser::{Block, BlockRef};
+use crate::registry::{CommandId, CommandRegistry};
+
+pub mod compiler;
+pub use compiler::*;
+
+/// A single instruction of a [`Program`].
+/// 
+/// Indices refer to the tables of the program they are part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Pushes `null`.
+    Nil,
+    /// Pushes a constant.
+    Const(u32),
+    /// Pushes the value of the result reference (`$`).
+    LoadRes,
+    /// Pushes the value of the context reference (`$$`).
+    LoadCtx,
+    /// Pushes the value of a local variable.
+    LoadVar(u32),
+    /// Assigns the value on top of the stack to a local variable, keeping it on the stack.
+    StoreVar(u32),
+    /// Appends the value on top of the stack to the list in a local variable, keeping it on the stack.
+    AppendVar(u32),
+    /// Sets the result reference (`$`) to the value on top of the stack, keeping it on the stack.
+    SetRes,
+    /// Pops a value and binds it to `$`, pushing the previous value of `$` in its place.
+    BindRes,
+    /// Pops a value and the previous value of `$` below it, restoring `$` and pushing the value again.
+    RestoreRes,
+    /// Discards the value on top of the stack.
+    Pop,
+    /// Pops the given number of values, pushing them as list.
+    List(u32),
+    /// Pops one value per key of the given key-list, pushing them as dict.
+    Dict(u32),
+    /// Pops an end and a start, pushing them as range; inclusive if true.
+    Range(bool),
+    /// Pops a value, pushing the named field of it.
+    Field(u32),
+    /// Pops an index and a value, pushing the indexed element of the value.
+    Index,
+    /// Fails if the value on top of the stack is `null`.
+    Unwrap,
+    /// Pops a value, pushing it arranged as grid.
+    Grid,
+    /// Defers the given expression, with the value on top of the stack bound to `$`.
+    Defer(BlockRef),
+    /// Invokes a command at a call-site with pre-bound arguments.
+    Invoke(u32),
+    /// Invokes a command by name, at a call-site with unbound arguments.
+    Call(u32),
+    /// Evaluates the given expression via the tree-walking interpreter.
+    Interpret(BlockRef),
+    /// Continues at the given instruction.
+    Jump(u32),
+    /// Continues at the given instruction if the value on top of the stack is truthy.
+    JumpIf(u32),
+    /// Continues at the given instruction if the value on top of the stack is falsy.
+    JumpUnless(u32),
+    /// Until the matching [`Op::Uncatch`], errors continue at the given instruction instead.
+    Catch(u32),
+    /// Ends the innermost [`Op::Catch`].
+    Uncatch,
+}
+
+/// A call-site of a command whose arguments were bound at compile time.
+#[derive(Debug, Clone)]
+pub struct BoundCall {
+    /// The invoked command.
+    pub id: CommandId,
+    /// The generation of the command when compiled; should it be registered again since,
+    /// the call is made by name instead.
+    pub generation: u32,
+    /// The number of values bound to parameters, in signature order.
+    pub values: u32,
+    /// The number of values bound to the variadic parameter.
+    pub rest: u32,
+    /// Where each value bound to a parameter came from; used to call the command by name instead.
+    pub args: Vec<BoundArg>,
+}
+
+/// Where a value bound to a parameter came from; see [`BoundCall::args`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoundArg {
+    /// A positional argument.
+    Positional,
+    /// A named argument (or flag).
+    Named(CompactString),
+    /// The default value of the parameter.
+    Default,
+}
+
+/// A call-site of a command that is looked up by name, when invoked.
+#[derive(Debug, Clone)]
+pub struct NamedCall {
+    /// The name of the command.
+    pub name: CompactString,
+    /// The number of positional arguments.
+    pub pos_args: u32,
+    /// The names of the named arguments, following the positional ones.
+    pub nom_args: Vec<CompactString>,
+}
+
+/// A block compiled into bytecode; created via [`compile`].
+#[derive(Debug, Clone)]
+pub struct Program {
+    /// The instructions.
+    pub(crate) code: Vec<Op>,
+    /// The constants.
+    pub(crate) constants: Vec<Value>,
+    /// The names of variables, fields and dict keys.
+    pub(crate) names: Vec<CompactString>,
+    /// The key-lists of dicts.
+    pub(crate) keys: Vec<Vec<u32>>,
+    /// The call-sites of bound commands.
+    pub(crate) bound: Vec<BoundCall>,
+    /// The call-sites of commands looked up by name.
+    pub(crate) named: Vec<NamedCall>,
+    /// The compiled block, for expressions that are interpreted.
+    pub(crate) block: Block,
+}
+
+impl Program {
+    /// Returns the instructions of the program.
+    pub fn code(&self) -> &[Op] {
+        &self.code
+    }
+    
+    /// Runs the program on a fresh [`Vm`].
+    pub fn run(&self, registry: &mut CommandRegistry, ctx: &mut Context) -> Result<Value, EvalError> {
+        Vm::default().run(self, registry, ctx)
+    }
+}
+
+/// An active error handler; see [`Op::Catch`].
+#[derive(Debug)]
+struct Handler {
+    /// The instruction to continue at.
+    pc: usize,
+    /// The height of the stack to return to.
+    sp: usize,
+    /// The value of `$` to restore.
+    result: Value,
+}
+
+/// A stack-based virtual machine running [`Program`]s.
+/// 
+/// Keeping a machine around between runs avoids reallocating its stacks.
+#[derive(Debug, Default)]
+pub struct Vm {
+    /// The value stack.
+    stack: Vec<Value>,
+    /// The active error handlers.
+    handlers: Vec<Handler>,
+    /// The expressions deferred via `~>`, together with the value to bind to `$`.
+    deferred: Vec<(BlockRef, Value)>,
+}
+
+impl Vm {
+    /// Creates a new machine.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Runs the program to completion, dispatching commands to the registry.
+    /// 
+    /// Just like [`Interpreter::run`], deferred expressions are evaluated afterwards.
+    pub fn run(&mut self, program: &Program, registry: &mut CommandRegistry, ctx: &mut Context) -> Result<Value, EvalError> {
+        program.block.entry().ok_or(EvalError::NoEntry)?;
+        self.stack.clear();
+        self.handlers.clear();
+        self.deferred.clear();
+        
+        let mut pc = 0;
+        while let Some(op) = program.code.get(pc) {
+            pc += 1;
+            if let Err(err) = self.step(*op, &mut pc, program, registry, ctx) {
+                let Some(handler) = self.handlers.pop() else {
+                    return Err(err)
+                };
+                self.stack.truncate(handler.sp);
+                ctx.result = handler.result;
+                pc = handler.pc;
+            }
+        }
+        
+        let value = self.stack.pop().unwrap_or_default();
+        let mut interp = Interpreter::new(&program.block, registry);
+        interp.deferred = std::mem::take(&mut self.deferred);
+        interp.run_deferred(ctx)?;
+        Ok(value)
+    }
+    
+    /// Executes a single instruction.
+    fn step(
+        &mut self,
+        op: Op,
+        pc: &mut usize,
+        program: &Program,
+        registry: &mut CommandRegistry,
+        ctx: &mut Context
+    ) -> Result<(), EvalError> {
+        let name = |i: u32| &program.names[i as usize];
+        match op {
+            Op::Nil => self.stack.push(Value::Nil),
+            Op::Const(i) => self.stack.push(program.constants[i as usize].clone()),
+            Op::LoadRes => self.stack.push(ctx.result.clone()),
+            Op::LoadCtx => self.stack.push(ctx.context.clone()),
+            Op::LoadVar(i) => {
+                let value = ctx.locals.get(name(i))
+                    .cloned()
+                    .ok_or_else(|| EvalError::UndefinedVariable(name(i).clone()))?;
+                self.stack.push(value);
+            },
+            Op::StoreVar(i) => {
+                let value = self.peek().clone();
+                ctx.locals.insert(name(i).clone(), value);
+            },
+            Op::AppendVar(i) => {
+                let value = self.peek().clone();
+                match ctx.locals.entry(name(i).clone()).or_insert(Value::Nil) {
+                    Value::List(list) => list.push(value),
+                    slot @ Value::Nil => *slot = Value::List(vec![value]),
+                    other => return Err(EvalError::ExpectButGot("a list to append to".into(), other.get_type_str().into())),
+                }
+            },
+            Op::SetRes => ctx.result = self.peek().clone(),
+            Op::BindRes => {
+                let value = self.pop();
+                let previous = std::mem::replace(&mut ctx.result, value);
+                self.stack.push(previous);
+            },
+            Op::RestoreRes => {
+                let value = self.pop();
+                ctx.result = self.pop();
+                self.stack.push(value);
+            },
+            Op::Pop => {
+                self.pop();
+            },
+            Op::List(len) => {
+                let list = self.stack.split_off(self.stack.len() - len as usize);
+                self.stack.push(Value::List(list));
+            },
+            Op::Dict(i) => {
+                let keys = &program.keys[i as usize];
+                let values = self.stack.split_off(self.stack.len() - keys.len());
+                let dict = keys.iter().map(|k| name(*k).clone()).zip(values).collect();
+                self.stack.push(Value::Dict(dict));
+            },
+            Op::Range(inclusive) => {
+                let end = self.pop();
+                let start = self.pop();
+                self.stack.push(Value::Range(Box::new(crate::values::RangeValue { start, end, inclusive })));
+            },
+            Op::Field(i) => {
+                let target = self.pop();
+                let value = Interpreter::new(&program.block, registry).eval_field(ctx, target, name(i))?;
+                self.stack.push(value);
+            },
+            Op::Index => {
+                let index = self.pop();
+                let target = self.pop();
+                let value = Interpreter::new(&program.block, registry).eval_index(ctx, target, index)?;
+                self.stack.push(value);
+            },
+            Op::Unwrap => if matches!(self.peek(), Value::Nil) {
+                return Err(EvalError::Unwrap)
+            },
+            Op::Grid => {
+                let value = self.pop();
+                self.stack.push(into_grid(value));
+            },
+            Op::Defer(br) => {
+                let value = self.peek().clone();
+                self.deferred.push((br, value));
+            },
+            Op::Invoke(i) => {
+                let call = &program.bound[i as usize];
+                let rest = self.stack.split_off(self.stack.len() - call.rest as usize);
+                let values = self.stack.split_off(self.stack.len() - call.values as usize);
+                
+                let command = registry.get(call.id);
+                let signature = &command.signature;
+                if command.generation != call.generation {
+                    // The command was registered again since; pass the arguments by name, as given.
+                    let mut args = Arguments::default();
+                    for (arg, value) in call.args.iter().zip(values) {
+                        match arg {
+                            BoundArg::Positional => args.pos_args.push(value),
+                            BoundArg::Named(key) => {
+                                args.nom_args.insert(key.clone(), value);
+                            },
+                            BoundArg::Default => (),
+                        }
+                    }
+                    args.pos_args.extend(rest);
+                    
+                    let name = signature.name.clone();
+                    let value = crate::eval::CommandHost::call(registry, ctx, &name, args)?;
+                    self.stack.push(value);
+                    return Ok(())
+                }
+                let params = signature.positional.iter().chain(&signature.named);
+                let values = params.zip(values)
+                    .map(|(param, value)| signature.check(param, value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let rest = match &signature.variadic {
+                    Some(param) => rest.into_iter()
+                        .map(|value| signature.check(param, value))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => rest,
+                };
+                
+                let value = registry.invoke_bound(ctx, call.id, values, rest)?;
+                self.stack.push(value);
+            },
+            Op::Call(i) => {
+                let call = &program.named[i as usize];
+                let nom_args = self.stack.split_off(self.stack.len() - call.nom_args.len());
+                let pos_args = self.stack.split_off(self.stack.len() - call.pos_args as usize);
+                let args = Arguments {
+                    pos_args,
+                    nom_args: call.nom_args.iter().cloned().zip(nom_args).collect(),
+                };
+                
+                let value = crate::eval::CommandHost::call(registry, ctx, &call.name, args)?;
+                self.stack.push(value);
+            },
+            Op::Interpret(br) => {
+                let mut interp = Interpreter::new(&program.block, registry);
+                let value = interp.eval(ctx, br);
+                self.deferred.append(&mut interp.deferred);
+                self.stack.push(value?);
+            },
+            Op::Jump(target) => *pc = target as usize,
+            Op::JumpIf(target) => if self.peek().is_truthy() {
+                *pc = target as usize;
+            },
+            Op::JumpUnless(target) => if !self.peek().is_truthy() {
+                *pc = target as usize;
+            },
+            Op::Catch(target) => self.handlers.push(Handler {
+                pc: target as usize,
+                sp: self.stack.len(),
+                result: ctx.result.clone(),
+            }),
+            Op::Uncatch => {
+                self.handlers.pop();
+            },
+        }
+        Ok(())
+    }
+    
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("the compiler balances the stack")
+    }
+    
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the compiler balances the stack")
+    }
+}
+
+#[cfg(test)]
+mod tests;
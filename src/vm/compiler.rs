@@ -0,0 +1,341 @@
+//! Compilation of [`Block`]s into [`Program`]s.
+
+use super::*;
+use crate::lexer::Literal;
+use crate::parser::{Arrow, CommandPath, Expression, FnCall};
+
+/// Compiles the block into a program, resolving commands via the registry and the given imports.
+/// 
+/// Calls whose arguments cannot be bound to the signature of their command,
+/// as well as calls to commands not (yet) in the registry, are looked up by name when invoked;
+/// they thus fail (or succeed) just like they would in the [`Interpreter`].
+/// 
+/// Commands defined via `def` or `alias` within the block are always looked up by name,
+/// as are commands registered again since the program was compiled.
+pub fn compile(block: &Block, registry: &CommandRegistry, imports: &[CommandPath]) -> Program {
+    let defined = block.iter()
+        .filter_map(|(_, expr)| match expr {
+            Expression::Def(def) => Some(def.name.to_str().into()),
+            _ => None,
+        })
+        .collect();
+    
+    let mut compiler = Compiler {
+        registry,
+        imports,
+        defined,
+        program: Program {
+            code: Vec::new(),
+            constants: Vec::new(),
+            names: Vec::new(),
+            keys: Vec::new(),
+            bound: Vec::new(),
+            named: Vec::new(),
+            block: block.clone(),
+        },
+    };
+    
+    match block.entry() {
+        Some(entry) => compiler.expr(block, entry),
+        None => compiler.emit(Op::Nil),
+    };
+    
+    compiler.program
+}
+
+/// The state of a compilation.
+struct Compiler<'r> {
+    /// The registry to resolve commands with.
+    registry: &'r CommandRegistry,
+    /// The namespaces and commands to resolve commands with.
+    imports: &'r [CommandPath],
+    /// The names of the commands defined within the block, which are only known once it runs.
+    defined: Vec<CompactString>,
+    /// The program being compiled.
+    program: Program,
+}
+
+impl Compiler<'_> {
+    /// Compiles an expression, leaving its value on the stack.
+    fn expr(&mut self, block: &Block, br: BlockRef) {
+        match block.get(br) {
+            Expression::Empty => self.emit(Op::Nil),
+            Expression::Value(Literal::RefRes) => self.emit(Op::LoadRes),
+            Expression::Value(Literal::RefCtx) => self.emit(Op::LoadCtx),
+            Expression::Value(Literal::RefVar(var)) => {
+                let var = self.name(var);
+                self.emit(Op::LoadVar(var))
+            },
+            Expression::Value(literal) => {
+                let constant = self.constant(Value::from(literal));
+                self.emit(Op::Const(constant))
+            },
+            Expression::FnCall(call) => self.call(block, br, call, None),
+            Expression::Range(start, end, inclusive) => {
+                self.expr(block, *start);
+                self.expr(block, *end);
+                self.emit(Op::Range(*inclusive))
+            },
+            Expression::Field(target, field) => {
+                self.expr(block, *target);
+                let field = self.name(field);
+                self.emit(Op::Field(field))
+            },
+            Expression::Index(target, index) => {
+                self.expr(block, *target);
+                self.expr(block, *index);
+                self.emit(Op::Index)
+            },
+            Expression::Method(target, call) => match block.get(*call) {
+                Expression::FnCall(call) => self.call(block, br, call, Some(*target)),
+                _ => self.emit(Op::Interpret(br)),
+            },
+            Expression::Try(target, true) => {
+                self.expr(block, *target);
+                self.emit(Op::Unwrap)
+            },
+            Expression::Try(target, false) => {
+                let catch = self.mark(Op::Catch(0));
+                self.expr(block, *target);
+                self.emit(Op::Uncatch);
+                let end = self.mark(Op::Jump(0));
+                self.patch(catch);
+                self.emit(Op::Nil);
+                self.patch(end);
+            },
+            Expression::Arrow(arrow, left, right) => self.arrow(block, br, *arrow, *left, *right),
+            Expression::Sequence(statements) => {
+                if statements.is_empty() {
+                    self.emit(Op::Nil);
+                }
+                for (i, statement) in statements.iter().enumerate() {
+                    if i > 0 {
+                        self.emit(Op::Pop);
+                    }
+                    self.expr(block, *statement);
+                    self.emit(Op::SetRes);
+                }
+            },
+            Expression::Pipe(_)
+            | Expression::Code(_)
+            | Expression::Def(_)
+            | Expression::Error => self.emit(Op::Interpret(br)),
+        }
+    }
+    
+    /// Compiles an arrow.
+    fn arrow(&mut self, block: &Block, br: BlockRef, arrow: Arrow, left: BlockRef, right: BlockRef) {
+        let var = match block.get(right) {
+            Expression::Value(Literal::RefVar(var)) => Some(self.name(var)),
+            _ => None,
+        };
+        
+        match (arrow, var) {
+            (Arrow::Assign, Some(var)) => {
+                self.expr(block, left);
+                self.emit(Op::StoreVar(var));
+            },
+            (Arrow::Append, Some(var)) => {
+                self.expr(block, left);
+                self.emit(Op::AppendVar(var));
+            },
+            (Arrow::Assign | Arrow::Append, None) => self.emit(Op::Interpret(br)),
+            (Arrow::Bind | Arrow::Grid, _) => {
+                self.expr(block, left);
+                if arrow == Arrow::Grid {
+                    self.emit(Op::Grid);
+                }
+                self.emit(Op::BindRes);
+                self.expr(block, right);
+                self.emit(Op::RestoreRes);
+            },
+            (Arrow::Defer, _) => {
+                self.expr(block, left);
+                self.emit(Op::Defer(right));
+            },
+        }
+    }
+    
+    /// Compiles a command invocation, optionally with a receiver as first positional argument.
+    fn call(&mut self, block: &Block, br: BlockRef, call: &FnCall, receiver: Option<BlockRef>) {
+        // Mirrors `Interpreter::eval_intrinsic`.
+        let intrinsic = match call.name.segments() {
+            [name] if receiver.is_none() => name.as_str(),
+            _ => "",
+        };
+        
+        match intrinsic {
+            "list" => {
+                for arg in &call.pos_args {
+                    self.expr(block, *arg);
+                }
+                self.emit(Op::List(call.pos_args.len() as u32));
+                return
+            },
+            "dict" => {
+                let mut nom_args: Vec<_> = call.nom_args.iter().collect();
+                nom_args.sort();
+                let mut keys = Vec::with_capacity(nom_args.len());
+                for (key, arg) in nom_args {
+                    self.expr(block, *arg);
+                    keys.push(self.name(key));
+                }
+                self.program.keys.push(keys);
+                self.emit(Op::Dict(self.program.keys.len() as u32 - 1));
+                return
+            },
+            "if-then" if call.pos_args.len() == 2 => {
+                self.expr(block, call.pos_args[0]);
+                let end = self.mark(Op::JumpUnless(0));
+                self.emit(Op::BindRes);
+                self.expr(block, call.pos_args[1]);
+                self.emit(Op::RestoreRes);
+                self.patch(end);
+                return
+            },
+            "if-else" if call.pos_args.len() == 2 => {
+                let catch = self.mark(Op::Catch(0));
+                self.expr(block, call.pos_args[0]);
+                self.emit(Op::Uncatch);
+                let end = self.mark(Op::JumpIf(0));
+                self.emit(Op::Pop);
+                self.patch(catch);
+                self.expr(block, call.pos_args[1]);
+                self.patch(end);
+                return
+            },
+            "use" => return self.emit(Op::Interpret(br)),
+            _ => (),
+        }
+        
+        // Closures are invoked by the interpreter itself.
+        if call.name == "call" {
+            return self.emit(Op::Interpret(br))
+        }
+        
+        let pos_args: Vec<BlockRef> = receiver.into_iter().chain(call.pos_args.iter().copied()).collect();
+        let name = call.name.to_str();
+        if let Some(id) = self.registry.resolve(&name, self.imports) {
+            let signature = &self.registry.get(id).signature;
+            let defined = self.defined.iter().any(|def| *def == *name || *def == signature.name);
+            if !defined && self.bound_call(block, id, call, &pos_args) {
+                return
+            }
+        }
+        
+        let mut nom_args: Vec<_> = call.nom_args.iter().collect();
+        nom_args.sort();
+        for arg in &pos_args {
+            self.expr(block, *arg);
+        }
+        for (_, arg) in &nom_args {
+            self.expr(block, **arg);
+        }
+        
+        self.program.named.push(NamedCall {
+            name: name.into(),
+            pos_args: pos_args.len() as u32,
+            nom_args: nom_args.into_iter().map(|(key, _)| key.clone()).collect(),
+        });
+        self.emit(Op::Call(self.program.named.len() as u32 - 1));
+    }
+    
+    /// Compiles a command invocation with its arguments bound to the signature of the command.
+    /// 
+    /// Returns false, without emitting anything, if the arguments cannot be bound.
+    fn bound_call(&mut self, block: &Block, id: CommandId, call: &FnCall, pos_args: &[BlockRef]) -> bool {
+        /// Where the value of a parameter comes from.
+        enum Slot<'a> {
+            Arg(BlockRef, BoundArg),
+            Default(&'a Value),
+        }
+        
+        let signature = &self.registry.get(id).signature;
+        if pos_args.len() > signature.positional.len() && signature.variadic.is_none() {
+            return false
+        }
+        if call.nom_args.keys().any(|key| signature.get_named(key).is_none()) {
+            return false
+        }
+        
+        let positional = signature.positional.iter().enumerate()
+            .map(|(i, param)| pos_args.get(i)
+                .map(|arg| Slot::Arg(*arg, BoundArg::Positional))
+                .or(param.default.as_ref().map(Slot::Default)));
+        let named = signature.named.iter()
+            .map(|param| call.nom_args.get(&param.name)
+                .map(|arg| Slot::Arg(*arg, BoundArg::Named(param.name.clone())))
+                .or(param.default.as_ref().map(Slot::Default)));
+        
+        let Some(slots) = positional.chain(named).collect::<Option<Vec<_>>>() else {
+            return false
+        };
+        let rest = pos_args.get(signature.positional.len()..).unwrap_or_default();
+        
+        let values = slots.len() as u32;
+        let mut args = Vec::with_capacity(slots.len());
+        for slot in slots {
+            match slot {
+                Slot::Arg(arg, from) => {
+                    self.expr(block, arg);
+                    args.push(from);
+                },
+                Slot::Default(value) => {
+                    let constant = self.constant(value.clone());
+                    self.emit(Op::Const(constant));
+                    args.push(BoundArg::Default);
+                },
+            }
+        }
+        for arg in rest {
+            self.expr(block, *arg);
+        }
+        
+        self.program.bound.push(BoundCall { id, generation: self.registry.get(id).generation, values, rest: rest.len() as u32, args });
+        self.emit(Op::Invoke(self.program.bound.len() as u32 - 1));
+        true
+    }
+    
+    /// Appends an instruction.
+    fn emit(&mut self, op: Op) {
+        self.program.code.push(op);
+    }
+    
+    /// Appends a jump (or catch), returning its position for [`Self::patch`].
+    fn mark(&mut self, op: Op) -> usize {
+        self.emit(op);
+        self.program.code.len() - 1
+    }
+    
+    /// Points the jump (or catch) at the given position to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let here = self.program.code.len() as u32;
+        match &mut self.program.code[at] {
+            Op::Jump(target)
+            | Op::JumpIf(target)
+            | Op::JumpUnless(target)
+            | Op::Catch(target) => *target = here,
+            op => unreachable!("cannot patch {op:?}"),
+        }
+    }
+    
+    /// Returns the index of the given constant, adding it if necessary.
+    fn constant(&mut self, value: Value) -> u32 {
+        let constants = &mut self.program.constants;
+        let index = constants.iter().position(|c| *c == value).unwrap_or_else(|| {
+            constants.push(value);
+            constants.len() - 1
+        });
+        index as u32
+    }
+    
+    /// Returns the index of the given name, adding it if necessary.
+    fn name(&mut self, name: &CompactString) -> u32 {
+        let names = &mut self.program.names;
+        let index = names.iter().position(|n| n == name).unwrap_or_else(|| {
+            names.push(name.clone());
+            names.len() - 1
+        });
+        index as u32
+    }
+}
@@ -0,0 +1,147 @@
+//! Tests to ensure compiled programs behave like the interpreter.
+
+use super::*;
+use crate::registry::{BindError, BoundArgs, Kind, Signature};
+
+fn registry() -> CommandRegistry {
+    let mut registry = crate::registry::testing::registry();
+    
+    registry.register(
+        Signature::new("is_odd")
+            .positional("number", Kind::Int),
+        |_: &mut Context, args: &BoundArgs| {
+            Ok(Value::Bool(args.get::<i64>("number")? % 2 == 1))
+        }
+    );
+    
+    registry.register(Signature::new("fail"), |_: &mut Context, _: &BoundArgs| Err(EvalError::Custom("failed".into())));
+    registry.register(Signature::new("nothing"), |_: &mut Context, _: &BoundArgs| Ok(Value::Nil));
+    registry
+}
+
+/// Runs the input both compiled and interpreted, asserting that both agree.
+fn run(input: &str) -> Result<Value, EvalError> {
+    let block = crate::parser::parse_script(input).expect("input should parse");
+    
+    let mut registry = registry();
+    let mut ctx = Context::new(Value::Int(42));
+    let interpreted = crate::eval::eval(&block, &mut registry, &mut ctx);
+    let interpreted_locals = ctx.locals.clone();
+    
+    let mut registry = self::registry();
+    let mut ctx = Context::new(Value::Int(42));
+    let compiled = compile(&block, &registry, &[]).run(&mut registry, &mut ctx);
+    
+    assert_eq!(format!("{compiled:?}"), format!("{interpreted:?}"), "compiled and interpreted results of `{input}` differ");
+    assert_eq!(ctx.locals, interpreted_locals, "compiled and interpreted locals of `{input}` differ");
+    compiled
+}
+
+#[test]
+fn vm_values() -> Result<(), EvalError> {
+    assert_eq!(run("$$")?, Value::Int(42));
+    assert_eq!(run("list 1 2 3")?, Value::List(vec![1.into(), 2.into(), 3.into()]));
+    assert_eq!(run("dict a=1 b=2")?, run("{b=2 a=1}")?);
+    assert_eq!(run("{a=1}.a")?, Value::Int(1));
+    assert_eq!(run("[1 2 3].[1]")?, Value::Int(2));
+    assert_eq!(run("(0..=9).end")?, Value::Int(9));
+    assert!(matches!(run("$nope"), Err(EvalError::UndefinedVariable(_))));
+    Ok(())
+}
+
+#[test]
+fn vm_commands() -> Result<(), EvalError> {
+    assert_eq!(run("+ 1 (+ 2 3)")?, Value::Int(6));
+    assert_eq!(run("= 1 + 2 + 3")?, Value::Int(6));
+    assert_eq!(run("heal bob")?, "bob +10 loudly".into());
+    assert_eq!(run("heal bob amount=5 +silent")?, "bob +5 silently".into());
+    assert_eq!(run("list bob.(heal amount=1)")?, Value::List(vec!["bob +1 loudly".into()]));
+    assert!(matches!(run("heal"), Err(EvalError::Bind(BindError::Missing(_, _)))));
+    assert!(matches!(run("heal 1"), Err(EvalError::Bind(BindError::Mismatch(_, _, _, _)))));
+    assert!(matches!(run("heal bob foo=1"), Err(EvalError::Bind(BindError::Unknown(_, _)))));
+    assert!(matches!(run("unknown"), Err(EvalError::UnknownCommand(_))));
+    assert_eq!(run("def heal $a: + $a 1; heal 1")?, Value::Int(2));
+    assert_eq!(run("alias twice: + _ _; twice 2 3")?, Value::Int(5));
+    Ok(())
+}
+
+#[test]
+fn vm_fallible() -> Result<(), EvalError> {
+    assert_eq!(run("(fail)?")?, Value::Nil);
+    assert!(run("(fail)?!").is_err());
+    assert!(matches!(run("(nothing)?!"), Err(EvalError::Unwrap)));
+    assert_eq!(run("+ 1 2 && + $ 1")?, Value::Int(4));
+    assert_eq!(run("nothing && + $ 1")?, Value::Nil);
+    assert_eq!(run("fail || + 3")?, Value::Int(3));
+    assert_eq!(run("+ 1 -> $x; (+ $x (fail))? -> $y; + $x 1")?, Value::Int(2));
+    Ok(())
+}
+
+#[test]
+fn vm_arrows() -> Result<(), EvalError> {
+    assert_eq!(run("+ 2 -> $x; + $x $x")?, Value::Int(4));
+    assert_eq!(run("+ 1 +> $l; + 2 +> $l; $l")?, Value::List(vec![1.into(), 2.into()]));
+    assert!(run("+ 1 -> $l; + 2 +> $l").is_err());
+    assert_eq!(run("+ 1 => + $ 1")?, Value::Int(2));
+    assert_eq!(run("+ 1 ~> + $ 1 +> $l; + 2 +> $l")?, Value::Int(2));
+    assert_eq!(run("+ 1 ~> + $ 1 -> $x; $x?")?, Value::Nil);
+    assert_eq!(run("[{a=1 b=2} {a=3}] #> $.[1]")?, Value::List(vec![1.into(), 2.into()]));
+    assert_eq!(run("0..3 | + $ 1")?, Value::List(vec![1.into(), 2.into(), 3.into()]));
+    Ok(())
+}
+
+#[test]
+fn vm_bound_calls() {
+    let block = crate::parse("heal bob +silent").expect("input should parse");
+    let registry = registry();
+    let program = compile(&block, &registry, &[]);
+    assert!(program.code().iter().any(|op| matches!(op, Op::Invoke(_))));
+    assert!(!program.code().iter().any(|op| matches!(op, Op::Call(_))));
+    
+    let block = crate::parse("spawn bob").expect("input should parse");
+    let program = compile(&block, &registry, &[]);
+    assert!(program.code().iter().any(|op| matches!(op, Op::Call(_))));
+    
+    let block = crate::parser::parse_script("def heal $a: + $a 1; heal bob").expect("input should parse");
+    let program = compile(&block, &registry, &[]);
+    assert!(!program.code().iter().any(|op| matches!(op, Op::Invoke(i) if program.bound[*i as usize].id == registry.resolve("heal", &[]).unwrap())));
+}
+
+#[test]
+fn vm_reuse() -> Result<(), EvalError> {
+    let block = crate::parse("+ $x 1 -> $x").expect("input should parse");
+    let mut registry = registry();
+    let program = compile(&block, &registry, &[]);
+    
+    let mut vm = Vm::new();
+    let mut ctx = Context::default();
+    ctx.locals.insert("x".into(), Value::Int(0));
+    for _ in 0..3 {
+        vm.run(&program, &mut registry, &mut ctx)?;
+    }
+    assert_eq!(ctx.locals.get("x"), Some(&Value::Int(3)));
+    
+    // Once the signature changes, the command is called by name instead.
+    registry.register(
+        Signature::new("+").positional("a", Kind::Int).positional("b", Kind::Int),
+        |_: &mut Context, args: &BoundArgs| Ok(Value::Int(args.get::<i64>("a")? - args.get::<i64>("b")?))
+    );
+    vm.run(&program, &mut registry, &mut ctx)?;
+    assert_eq!(ctx.locals.get("x"), Some(&Value::Int(2)));
+    
+    // Even if only the names of its parameters change.
+    let block = crate::parse("heal bob amount=5").expect("input should parse");
+    let program = compile(&block, &registry, &[]);
+    registry.register(
+        Signature::new("heal")
+            .positional("target", Kind::Str)
+            .named("count", Kind::Int, 10)
+            .flag("silent"),
+        |_: &mut Context, args: &BoundArgs| Ok(Value::Int(args.get("count")?))
+    );
+    let interpreted = crate::eval::eval(&block, &mut registry, &mut ctx);
+    let compiled = vm.run(&program, &mut registry, &mut ctx);
+    assert_eq!(format!("{compiled:?}"), format!("{interpreted:?}"));
+    assert!(matches!(compiled, Err(EvalError::Bind(BindError::Unknown(c, p))) if c == "heal" && p == "amount"));
+    Ok(())
+}